@@ -0,0 +1,527 @@
+mod spec;
+
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anchor_lang::{prelude::Pubkey, Discriminator};
+use anchor_spl::{token, token_2022};
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use prediction_market_client::{
+    access,
+    instructions::{self, Access},
+    pda::{find_allowlist_address, find_market_address},
+    program::{helpers::PRECISION, states::Market},
+    quote, state, MarketAddresses, PROGRAM_ID,
+};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::Instruction,
+    signature::{read_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+
+use crate::spec::MarketSpec;
+
+/// Devnet USDC used by the test suite.
+const DEFAULT_MINT_USDC: &str = "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU";
+const INITIALIZE_COMPUTE_UNITS: u32 = 300_000;
+const EXPIRATION_SECS: i64 = 60;
+
+#[derive(Parser)]
+#[command(about = "Operate prediction_market_amm markets")]
+struct Cli {
+    #[arg(long, env = "RPC_URL", default_value = "http://127.0.0.1:8899")]
+    url: String,
+    #[arg(long, env = "KEYPAIR", default_value = "~/.config/solana/id.json")]
+    keypair: String,
+    /// Collateral for `create` when the spec names none; existing markets
+    /// record their own.
+    #[arg(long, default_value = DEFAULT_MINT_USDC)]
+    mint_usdc: Pubkey,
+    /// `create` makes outcome mints under the Token-2022 program. The
+    /// collateral's program is read from its mint.
+    #[arg(long)]
+    token_2022: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a market from a TOML or JSON spec.
+    Create {
+        spec: PathBuf,
+    },
+    /// Set a market's title, tags and rules from the `[info]` section of a
+    /// spec; only possible before trading opens.
+    SetInfo {
+        seed: u64,
+        spec: PathBuf,
+    },
+    /// Mint outcome liquidity into a market's vaults.
+    AddLiquidity {
+        seed: u64,
+        yes: u64,
+        no: u64,
+    },
+    Lock {
+        seed: u64,
+    },
+    Unlock {
+        seed: u64,
+    },
+    Settle {
+        seed: u64,
+        #[arg(long)]
+        unresolved: bool,
+        /// Winning side, required to resolve; renames the outcome tokens to
+        /// WON/LOST unless `--keep-token-names` is set.
+        #[arg(
+            long,
+            conflicts_with = "unresolved",
+            required_unless_present = "unresolved"
+        )]
+        winner: Option<Outcome>,
+        #[arg(long)]
+        keep_token_names: bool,
+    },
+    /// Gate trading to the wallets listed one per line in `allowlist`, or
+    /// reopen the market when it is omitted.
+    SetAccessRoot {
+        seed: u64,
+        allowlist: Option<PathBuf>,
+    },
+    /// Issue an allowlist entry to a wallet.
+    Grant {
+        seed: u64,
+        user: Pubkey,
+    },
+    /// Close a wallet's allowlist entry.
+    Revoke {
+        seed: u64,
+        user: Pubkey,
+    },
+    /// List every market with its spot prices.
+    List,
+    /// Dump a market's state.
+    Show {
+        seed: u64,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Outcome {
+    Yes,
+    No,
+}
+
+struct Operator {
+    rpc: RpcClient,
+    payer: Keypair,
+    mint_usdc: Pubkey,
+    token_program: Pubkey,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let ctx = Operator {
+        rpc: RpcClient::new_with_commitment(cli.url, CommitmentConfig::confirmed()),
+        payer: read_keypair_file(expand_home(&cli.keypair))
+            .map_err(|err| anyhow::anyhow!("reading keypair {}: {err}", cli.keypair))?,
+        mint_usdc: cli.mint_usdc,
+        token_program: match cli.token_2022 {
+            true => token_2022::ID,
+            false => token::ID,
+        },
+    };
+
+    match cli.command {
+        Command::Create { spec } => create(&ctx, &MarketSpec::load(&spec)?),
+        Command::SetInfo { seed, spec } => {
+            let Some(info) = MarketSpec::load(&spec)?.market_info_args()? else {
+                bail!("{} has no [info] section", spec.display());
+            };
+            ctx.send(vec![instructions::set_market_info(
+                &ctx.addresses(seed)?,
+                ctx.payer.pubkey(),
+                info,
+            )])
+        }
+        Command::AddLiquidity { seed, yes, no } => {
+            let market = ctx.addresses(seed)?;
+            let mut ixs = ctx.open_position(&market)?;
+            ixs.push(instructions::add_liquidity(
+                &market,
+                ctx.payer.pubkey(),
+                yes,
+                no,
+                now() + EXPIRATION_SECS,
+                ctx.access(&market)?,
+                true,
+            ));
+            ctx.send(ixs)
+        }
+        Command::Lock { seed } => ctx.send(vec![instructions::lock(
+            &ctx.addresses(seed)?,
+            ctx.payer.pubkey(),
+        )]),
+        Command::Unlock { seed } => ctx.send(vec![instructions::unlock(
+            &ctx.addresses(seed)?,
+            ctx.payer.pubkey(),
+        )]),
+        Command::Settle {
+            seed,
+            unresolved,
+            winner,
+            keep_token_names,
+        } => ctx.send(vec![instructions::settle(
+            &ctx.addresses(seed)?,
+            ctx.payer.pubkey(),
+            !unresolved,
+            winner.map(|winner| matches!(winner, Outcome::Yes)),
+            !keep_token_names,
+        )]),
+        Command::SetAccessRoot { seed, allowlist } => {
+            let access_root = match allowlist {
+                Some(path) => access::merkle_root(&read_allowlist(&path)?),
+                None => None,
+            };
+            ctx.send(vec![instructions::set_access_root(
+                &ctx.addresses(seed)?,
+                ctx.payer.pubkey(),
+                access_root,
+            )])
+        }
+        Command::Grant { seed, user } => ctx.send(vec![instructions::grant_access(
+            &ctx.addresses(seed)?,
+            ctx.payer.pubkey(),
+            user,
+        )]),
+        Command::Revoke { seed, user } => ctx.send(vec![instructions::revoke_access(
+            &ctx.addresses(seed)?,
+            ctx.payer.pubkey(),
+            user,
+        )]),
+        Command::List => list(&ctx),
+        Command::Show { seed } => show(&ctx, seed),
+    }
+}
+
+impl Operator {
+    fn market(&self, seed: u64) -> Result<Market> {
+        let address = find_market_address(seed).0;
+        let data = self
+            .rpc
+            .get_account_data(&address)
+            .with_context(|| format!("fetching market {address}"))?;
+        Ok(state::decode_market(&data)?)
+    }
+
+    /// Addresses of an existing market, built from the collateral mint and
+    /// outcome metadata it records.
+    fn addresses(&self, seed: u64) -> Result<MarketAddresses> {
+        self.market_addresses(&self.market(seed)?)
+    }
+
+    fn market_addresses(&self, market: &Market) -> Result<MarketAddresses> {
+        Ok(MarketAddresses::from_market(
+            market,
+            self.collateral_token_program(&market.mint_usdc)?,
+        ))
+    }
+
+    /// Addresses of a market `create` is about to initialize.
+    fn addresses_for(&self, seed: u64, mint_usdc: Pubkey) -> Result<MarketAddresses> {
+        Ok(MarketAddresses::with_token_programs(
+            seed,
+            mint_usdc,
+            self.token_program,
+            self.collateral_token_program(&mint_usdc)?,
+        ))
+    }
+
+    fn collateral_token_program(&self, mint_usdc: &Pubkey) -> Result<Pubkey> {
+        Ok(self
+            .rpc
+            .get_account(mint_usdc)
+            .with_context(|| format!("fetching collateral mint {mint_usdc}"))?
+            .owner)
+    }
+
+    fn send(&self, instructions: Vec<Instruction>) -> Result<()> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let tx = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&self.payer.pubkey()),
+            &[&self.payer],
+            blockhash,
+        );
+
+        let signature = self
+            .rpc
+            .send_and_confirm_transaction(&tx)
+            .context("sending transaction")?;
+        println!("{signature}");
+        Ok(())
+    }
+
+    /// Uses the operator's allowlist entry when the market has one for them.
+    fn access(&self, market: &MarketAddresses) -> Result<Access> {
+        let entry = find_allowlist_address(&market.market, &self.payer.pubkey()).0;
+        let exists = self
+            .rpc
+            .get_account_with_commitment(&entry, self.rpc.commitment())?
+            .value
+            .is_some();
+
+        Ok(if exists {
+            Access::Allowlisted
+        } else {
+            Access::Open
+        })
+    }
+
+    /// Opens the operator's position in the market unless they already have
+    /// one.
+    fn open_position(&self, market: &MarketAddresses) -> Result<Vec<Instruction>> {
+        let position = market.position(&self.payer.pubkey());
+        let exists = self
+            .rpc
+            .get_account_with_commitment(&position, self.rpc.commitment())?
+            .value
+            .is_some();
+
+        Ok(match exists {
+            true => vec![],
+            false => vec![instructions::open_position(market, self.payer.pubkey())],
+        })
+    }
+
+    fn token_balance(&self, account: &Pubkey) -> Result<u64> {
+        let balance = self.rpc.get_token_account_balance(account)?;
+        Ok(balance.amount.parse()?)
+    }
+}
+
+fn create(ctx: &Operator, spec: &MarketSpec) -> Result<()> {
+    let seed = spec.seed.unwrap_or_else(|| now() as u64);
+    let mint_usdc = match &spec.mint_usdc {
+        Some(mint) => Pubkey::from_str(mint)?,
+        None => ctx.mint_usdc,
+    };
+    let treasury = match &spec.treasury {
+        Some(treasury) => Pubkey::from_str(treasury)?,
+        None => ctx.payer.pubkey(),
+    };
+
+    if spec.token_extensions && ctx.token_program != token_2022::ID {
+        bail!("token_extensions markets need --token-2022");
+    }
+
+    let market = ctx.addresses_for(seed, mint_usdc)?;
+
+    ctx.send(vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(INITIALIZE_COMPUTE_UNITS),
+        instructions::initialize(
+            &market,
+            ctx.payer.pubkey(),
+            treasury,
+            spec.initialize_args(),
+        ),
+    ])?;
+
+    if let Some(info) = spec.market_info_args()? {
+        ctx.send(vec![instructions::set_market_info(
+            &market,
+            ctx.payer.pubkey(),
+            info,
+        )])?;
+    }
+
+    println!("market {} (seed {seed})", market.market);
+    Ok(())
+}
+
+fn list(ctx: &Operator) -> Result<()> {
+    let accounts = ctx.rpc.get_program_accounts_with_config(
+        &PROGRAM_ID,
+        RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                0,
+                &Market::DISCRIMINATOR,
+            ))]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )?;
+
+    println!(
+        "{:<44} {:>20} {:<32} {:>8} {:>8} status",
+        "market", "seed", "name", "yes", "no"
+    );
+
+    for (address, account) in accounts {
+        let market = state::decode_market(&account.data)?;
+        let addresses = ctx.market_addresses(&market)?;
+
+        let (yes, no) = match (
+            ctx.token_balance(&addresses.vault_yes),
+            ctx.token_balance(&addresses.vault_no),
+        ) {
+            (Ok(yes), Ok(no)) => (
+                format_price(quote::spot_price(&market, yes, no, true)?),
+                format_price(quote::spot_price(&market, yes, no, false)?),
+            ),
+            _ => ("-".to_string(), "-".to_string()),
+        };
+
+        println!(
+            "{:<44} {:>20} {:<32} {:>8} {:>8} {}",
+            address,
+            market.seed,
+            market.market_name,
+            yes,
+            no,
+            status(&market)
+        );
+    }
+
+    Ok(())
+}
+
+fn show(ctx: &Operator, seed: u64) -> Result<()> {
+    let market = ctx.market(seed)?;
+    let addresses = ctx.market_addresses(&market)?;
+
+    println!("market           {}", addresses.market);
+    println!("name             {}", market.market_name);
+    println!("seed             {}", market.seed);
+    println!("mint_yes         {}", market.mint_yes);
+    println!("mint_no          {}", market.mint_no);
+    println!("mint_usdc        {}", market.mint_usdc);
+    println!("total_liquidity  {}", market.total_liquidity);
+    println!("fee              {}", market.fee);
+    println!("end_time         {}", market.end_time);
+    println!("keeper_tip_bps   {}", market.keeper_tip_bps);
+    println!("payer            {}", market.payer);
+    println!("treasury         {}", market.treasury);
+    println!("authority        {}", market.authority);
+    println!("initial_price    {}", format_price(market.initial_price));
+    println!("subsidy          {}", market.subsidy);
+    println!("decimals         {}", market.collateral_decimals);
+    println!("outcome_metadata {:?}", market.outcome_metadata);
+    println!(
+        "winner           {}",
+        match market.winner {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "-",
+        }
+    );
+    println!(
+        "access_root      {}",
+        market
+            .access_root
+            .map(|root| root.iter().map(|byte| format!("{byte:02x}")).collect())
+            .unwrap_or_else(|| "-".to_string())
+    );
+    println!("status           {}", status(&market));
+    println!("settled_at       {}", market.settled_at);
+    println!("claim_window     {}s", market.claim_window);
+
+    if let Ok(data) = ctx.rpc.get_account_data(&addresses.market_info) {
+        let info = state::decode_market_info(&data)?;
+        println!("title            {}", info.title);
+        println!("category         {:?}", info.category);
+        println!("tags             {:?}", info.tags);
+        println!("source           {}", info.resolution_source);
+        println!(
+            "rules_hash       {}",
+            info.rules_hash
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+        );
+        println!("event_id         {}", info.event_id);
+    }
+
+    let yes = ctx.token_balance(&addresses.vault_yes)?;
+    let no = ctx.token_balance(&addresses.vault_no)?;
+    let usdc = ctx.token_balance(&addresses.vault_usdc)?;
+
+    println!("vault_yes        {yes}");
+    println!("vault_no         {no}");
+    println!("vault_usdc       {usdc}");
+    println!(
+        "price_yes        {}",
+        format_price(quote::spot_price(&market, yes, no, true)?)
+    );
+    println!(
+        "price_no         {}",
+        format_price(quote::spot_price(&market, yes, no, false)?)
+    );
+
+    if let Ok(data) = ctx.rpc.get_account_data(&addresses.price_history) {
+        let history = state::decode_price_history(&data)?;
+        if let Ok(latest) = history.latest() {
+            println!("last_update_slot {}", latest.slot);
+        }
+    }
+
+    Ok(())
+}
+
+fn status(market: &Market) -> &'static str {
+    if market.settled {
+        "settled"
+    } else if market.locked {
+        "locked"
+    } else if now() > market.end_time {
+        "expired"
+    } else {
+        "open"
+    }
+}
+
+fn format_price(price: u64) -> String {
+    format!("{:.4}", price as f64 / 10u64.pow(PRECISION) as f64)
+}
+
+fn read_allowlist(path: &PathBuf) -> Result<Vec<Pubkey>> {
+    std::fs::read_to_string(path)
+        .with_context(|| format!("reading allowlist {}", path.display()))?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| Pubkey::from_str(line).with_context(|| format!("invalid pubkey {line}")))
+        .collect()
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
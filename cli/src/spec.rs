@@ -0,0 +1,191 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use prediction_market_client::{
+    instructions::InitializeArgs,
+    program::states::{CircuitBreaker, MarketInfoArgs, MarketLimits, MarketTag, OutcomeMetadata},
+};
+use serde::Deserialize;
+use solana_sdk::hash::hash;
+
+#[derive(Debug, Deserialize)]
+pub struct TokenSpec {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+/// Market definition read by `create`, as TOML or JSON.
+#[derive(Debug, Deserialize)]
+pub struct MarketSpec {
+    pub seed: Option<u64>,
+    pub name: String,
+    pub fee: u16,
+    pub end_time: i64,
+    #[serde(default)]
+    pub keeper_tip_bps: u16,
+    /// Opening YES probability, scaled by 10^6.
+    #[serde(default = "default_initial_price")]
+    pub initial_price: u64,
+    /// Minimum seconds between stored price observations; the TWAP history
+    /// covers 32 of them.
+    #[serde(default = "default_observation_interval")]
+    pub observation_interval: u32,
+    /// Seconds after settlement that winners have to claim before
+    /// `close_market` may sweep what is left; 0 waits for every claim.
+    #[serde(default)]
+    pub claim_window: u32,
+    pub treasury: Option<String>,
+    pub mint_usdc: Option<String>,
+    pub yes: TokenSpec,
+    pub no: TokenSpec,
+    /// Keep outcome token metadata on Token-2022 mints instead of Metaplex.
+    #[serde(default)]
+    pub token_extensions: bool,
+    #[serde(default)]
+    pub limits: LimitsSpec,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerSpec,
+    pub info: Option<InfoSpec>,
+}
+
+/// Description stored in the market's `MarketInfo` account.
+#[derive(Debug, Deserialize)]
+pub struct InfoSpec {
+    pub title: String,
+    pub category: TagSpec,
+    #[serde(default)]
+    pub tags: Vec<TagSpec>,
+    pub resolution_source: String,
+    /// Rules text file, relative to the spec; only its SHA-256 goes on-chain.
+    pub rules: PathBuf,
+    #[serde(default)]
+    pub event_id: String,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagSpec {
+    Sport,
+    League,
+    Crypto,
+    Politics,
+    Other,
+}
+
+impl From<TagSpec> for MarketTag {
+    fn from(tag: TagSpec) -> Self {
+        match tag {
+            TagSpec::Sport => MarketTag::Sport,
+            TagSpec::League => MarketTag::League,
+            TagSpec::Crypto => MarketTag::Crypto,
+            TagSpec::Politics => MarketTag::Politics,
+            TagSpec::Other => MarketTag::Other,
+        }
+    }
+}
+
+/// Caps in USDC base units; omitted or zero means uncapped.
+#[derive(Debug, Default, Deserialize)]
+pub struct LimitsSpec {
+    #[serde(default)]
+    pub max_trade_size: u64,
+    #[serde(default)]
+    pub max_user_exposure: u64,
+    #[serde(default)]
+    pub max_open_interest: u64,
+}
+
+/// Price moves are probabilities scaled by 10^6; omitted or zero disables a check.
+#[derive(Debug, Default, Deserialize)]
+pub struct CircuitBreakerSpec {
+    #[serde(default)]
+    pub max_price_move: u64,
+    #[serde(default)]
+    pub window_slots: u64,
+    #[serde(default)]
+    pub max_price_impact: u64,
+}
+
+fn default_initial_price() -> u64 {
+    500_000
+}
+
+fn default_observation_interval() -> u32 {
+    60
+}
+
+impl MarketSpec {
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("reading market spec {}", path.display()))?;
+
+        let mut spec: Self = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&raw).context("parsing TOML market spec")?,
+            Some("json") => serde_json::from_str(&raw).context("parsing JSON market spec")?,
+            _ => bail!("market spec must be a .toml or .json file"),
+        };
+
+        if let (Some(info), Some(dir)) = (&mut spec.info, path.parent()) {
+            info.rules = dir.join(&info.rules);
+        }
+        Ok(spec)
+    }
+
+    pub fn market_info_args(&self) -> Result<Option<MarketInfoArgs>> {
+        let Some(info) = &self.info else {
+            return Ok(None);
+        };
+
+        let rules = fs::read(&info.rules)
+            .with_context(|| format!("reading rules {}", info.rules.display()))?;
+
+        Ok(Some(MarketInfoArgs {
+            title: info.title.clone(),
+            category: info.category.into(),
+            tags: info.tags.iter().map(|&tag| tag.into()).collect(),
+            resolution_source: info.resolution_source.clone(),
+            rules_hash: hash(&rules).to_bytes(),
+            event_id: info.event_id.clone(),
+        }))
+    }
+
+    pub fn initialize_args(&self) -> InitializeArgs {
+        InitializeArgs {
+            name: self.name.clone(),
+            token_yes_name: self.yes.name.clone(),
+            token_yes_symbol: self.yes.symbol.clone(),
+            token_no_name: self.no.name.clone(),
+            token_no_symbol: self.no.symbol.clone(),
+            token_yes_uri: self.yes.uri.clone(),
+            token_no_uri: self.no.uri.clone(),
+            fee: self.fee,
+            end_time: self.end_time,
+            keeper_tip_bps: self.keeper_tip_bps,
+            limits: MarketLimits {
+                max_trade_size: self.limits.max_trade_size,
+                max_user_exposure: self.limits.max_user_exposure,
+                max_open_interest: self.limits.max_open_interest,
+            },
+            circuit_breaker: CircuitBreaker {
+                max_price_move: self.circuit_breaker.max_price_move,
+                window_slots: self.circuit_breaker.window_slots,
+                max_price_impact: self.circuit_breaker.max_price_impact,
+            },
+            initial_price: self.initial_price,
+            outcome_metadata: self.outcome_metadata(),
+            observation_interval: self.observation_interval,
+            claim_window: self.claim_window,
+        }
+    }
+
+    pub fn outcome_metadata(&self) -> OutcomeMetadata {
+        match self.token_extensions {
+            true => OutcomeMetadata::TokenExtensions,
+            false => OutcomeMetadata::Metaplex,
+        }
+    }
+}
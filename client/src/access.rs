@@ -0,0 +1,45 @@
+use anchor_lang::prelude::Pubkey;
+use prediction_market_amm::helpers::{access_leaf, hash_pair};
+
+/// Each level of the tree, leaves first. An odd node out is carried up as is.
+fn levels(users: &[Pubkey]) -> Vec<Vec<[u8; 32]>> {
+    let mut levels = vec![users.iter().map(access_leaf).collect::<Vec<_>>()];
+
+    while levels.last().is_some_and(|level| level.len() > 1) {
+        let next = levels
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => hash_pair(a, b),
+                [a] => *a,
+                _ => unreachable!(),
+            })
+            .collect();
+        levels.push(next);
+    }
+
+    levels
+}
+
+/// Root to store as a market's `access_root`, or `None` for no users.
+pub fn merkle_root(users: &[Pubkey]) -> Option<[u8; 32]> {
+    levels(users)
+        .last()
+        .and_then(|level| level.first().copied())
+}
+
+/// Proof that `user` is one of `users`, to pass as [`crate::instructions::Access::Proof`].
+pub fn merkle_proof(users: &[Pubkey], user: &Pubkey) -> Option<Vec<[u8; 32]>> {
+    let mut index = users.iter().position(|candidate| candidate == user)?;
+    let mut proof = Vec::new();
+
+    for level in levels(users).iter().take_while(|level| level.len() > 1) {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        index /= 2;
+    }
+
+    Some(proof)
+}
@@ -0,0 +1,705 @@
+use anchor_lang::{
+    err,
+    prelude::{AccountMeta, Pubkey},
+    solana_program::{instruction::Instruction, system_program, sysvar},
+    InstructionData, Result, ToAccountMetas,
+};
+use anchor_spl::{associated_token, metadata::mpl_token_metadata};
+use prediction_market_amm::{
+    accounts,
+    error::MarketError,
+    helpers::LimitOrder,
+    instruction,
+    states::{CircuitBreaker, MarketInfoArgs, MarketLimits, OutcomeMetadata},
+    ID,
+};
+
+use crate::pda::{find_allowlist_address, find_event_authority_address, MarketAddresses};
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// How a trader proves they may use a market gated by `access_root`.
+#[derive(Clone, Debug, Default)]
+pub enum Access {
+    /// The market is not gated.
+    #[default]
+    Open,
+    /// The trader holds an allowlist entry issued by the market authority.
+    Allowlisted,
+    /// Merkle proof of the trader's pubkey, see [`crate::access::merkle_proof`].
+    Proof(Vec<[u8; 32]>),
+}
+
+impl Access {
+    fn resolve(self, market: &MarketAddresses, user: &Pubkey) -> (Option<Pubkey>, Vec<[u8; 32]>) {
+        match self {
+            Access::Open => (None, Vec::new()),
+            Access::Allowlisted => (
+                Some(find_allowlist_address(&market.market, user).0),
+                Vec::new(),
+            ),
+            Access::Proof(proof) => (None, proof),
+        }
+    }
+}
+
+fn event_authority() -> Pubkey {
+    find_event_authority_address().0
+}
+
+#[derive(Clone, Debug)]
+pub struct InitializeArgs {
+    pub name: String,
+    pub token_yes_name: String,
+    pub token_yes_symbol: String,
+    pub token_no_name: String,
+    pub token_no_symbol: String,
+    pub token_yes_uri: String,
+    pub token_no_uri: String,
+    pub fee: u16,
+    pub end_time: i64,
+    pub keeper_tip_bps: u16,
+    pub limits: MarketLimits,
+    pub circuit_breaker: CircuitBreaker,
+    /// Opening YES price, scaled by 10^PRECISION.
+    pub initial_price: u64,
+    /// `TokenExtensions` needs addresses built for the Token-2022 program.
+    pub outcome_metadata: OutcomeMetadata,
+    /// Minimum seconds between stored price observations.
+    pub observation_interval: u32,
+    /// Seconds after settlement before unclaimed winnings may be swept by
+    /// `close_market`; 0 waits for every claim.
+    pub claim_window: u32,
+}
+
+pub fn initialize(
+    market: &MarketAddresses,
+    signer: Pubkey,
+    treasury: Pubkey,
+    args: InitializeArgs,
+) -> Instruction {
+    let metaplex = args.outcome_metadata == OutcomeMetadata::Metaplex;
+
+    build(
+        accounts::Initialize {
+            signer,
+            mint_yes: market.mint_yes,
+            mint_no: market.mint_no,
+            mint_usdc: market.mint_usdc,
+            treasury,
+            vault_yes: market.vault_yes,
+            vault_no: market.vault_no,
+            vault_usdc: market.vault_usdc,
+            // Wrapped SOL subsidies come from the signer's lamports.
+            signer_ata_usdc: market.collateral_accounts(&signer).0,
+            metadata_yes: metaplex.then_some(market.metadata_yes),
+            metadata_no: metaplex.then_some(market.metadata_no),
+            market: market.market,
+            price_history: market.price_history,
+            system_program: system_program::ID,
+            token_program: market.token_program,
+            collateral_token_program: market.collateral_token_program,
+            token_metadata_program: metaplex.then_some(mpl_token_metadata::ID),
+            associated_token_program: associated_token::ID,
+            rent: sysvar::rent::ID,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::Initialize {
+            seed: market.seed,
+            name: args.name,
+            token_yes_name: args.token_yes_name,
+            token_yes_symbol: args.token_yes_symbol,
+            token_no_name: args.token_no_name,
+            token_no_symbol: args.token_no_symbol,
+            token_yes_uri: args.token_yes_uri,
+            token_no_uri: args.token_no_uri,
+            fee: args.fee,
+            end_time: args.end_time,
+            keeper_tip_bps: args.keeper_tip_bps,
+            limits: args.limits,
+            circuit_breaker: args.circuit_breaker,
+            initial_price: args.initial_price,
+            outcome_metadata: args.outcome_metadata,
+            observation_interval: args.observation_interval,
+            claim_window: args.claim_window,
+        },
+    )
+}
+
+pub fn add_liquidity(
+    market: &MarketAddresses,
+    user: Pubkey,
+    max_yes: u64,
+    max_no: u64,
+    expiration: i64,
+    access: Access,
+    has_position: bool,
+) -> Instruction {
+    let (allowlist_entry, proof) = access.resolve(market, &user);
+
+    build(
+        accounts::Deposit {
+            user,
+            mint_yes: market.mint_yes,
+            mint_no: market.mint_no,
+            mint_usdc: market.mint_usdc,
+            vault_yes: market.vault_yes,
+            vault_no: market.vault_no,
+            vault_usdc: market.vault_usdc,
+            // Wrapped SOL deposits come from the user's lamports.
+            user_ata_usdc: market.collateral_accounts(&user).0,
+            market: market.market,
+            allowlist_entry,
+            position: has_position.then(|| market.position(&user)),
+            token_program: market.token_program,
+            collateral_token_program: market.collateral_token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::AddLiquidity {
+            max_yes,
+            max_no,
+            expiration,
+            proof,
+        },
+    )
+}
+
+/// Opens `user`'s position in the market. The trade, deposit and claim
+/// builders pass it when told `has_position`; markets capping user exposure
+/// need one before a user's first trade or deposit.
+pub fn open_position(market: &MarketAddresses, user: Pubkey) -> Instruction {
+    build(
+        accounts::OpenPosition {
+            user,
+            market: market.market,
+            position: market.position(&user),
+            system_program: system_program::ID,
+        },
+        instruction::OpenPosition,
+    )
+}
+
+/// Returns a finalized position's rent, or any position's once its market is
+/// closed.
+pub fn close_position_account(market: &MarketAddresses, user: Pubkey) -> Instruction {
+    build(
+        accounts::ClosePositionAccount {
+            user,
+            market: market.market,
+            position: market.position(&user),
+        },
+        instruction::ClosePositionAccount,
+    )
+}
+
+fn swap_accounts(
+    market: &MarketAddresses,
+    user: Pubkey,
+    allowlist_entry: Option<Pubkey>,
+    has_position: bool,
+) -> accounts::Swap {
+    let (user_ata_usdc, unwrap_account) = market.collateral_accounts(&user);
+
+    accounts::Swap {
+        user,
+        mint_yes: market.mint_yes,
+        mint_no: market.mint_no,
+        mint_usdc: market.mint_usdc,
+        vault_yes: market.vault_yes,
+        vault_no: market.vault_no,
+        vault_usdc: market.vault_usdc,
+        user_ata_yes: market.user_ata_yes(&user),
+        user_ata_no: market.user_ata_no(&user),
+        user_ata_usdc,
+        unwrap_account,
+        market: market.market,
+        price_history: market.price_history,
+        position: has_position.then(|| market.position(&user)),
+        allowlist_entry,
+        token_program: market.token_program,
+        collateral_token_program: market.collateral_token_program,
+        associated_token_program: associated_token::ID,
+        system_program: system_program::ID,
+        event_authority: event_authority(),
+        program: ID,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn swap(
+    market: &MarketAddresses,
+    user: Pubkey,
+    is_usdc_to_token: bool,
+    amount: u64,
+    is_yes: bool,
+    min_out: u64,
+    expiration: i64,
+    access: Access,
+    limit: Option<LimitOrder>,
+    has_position: bool,
+) -> Instruction {
+    let (allowlist_entry, proof) = access.resolve(market, &user);
+
+    build(
+        swap_accounts(market, user, allowlist_entry, has_position),
+        instruction::Swap {
+            is_usdc_to_token,
+            amount,
+            is_yes,
+            min_out,
+            expiration,
+            proof,
+            limit,
+        },
+    )
+}
+
+/// Moves the outcome's price to `target_price`. Gated markets only admit
+/// `allowlisted` users here, Merkle proofs are not accepted.
+pub fn trade_to_price(
+    market: &MarketAddresses,
+    user: Pubkey,
+    target_price: u64,
+    is_yes: bool,
+    max_spend: u64,
+    allowlisted: bool,
+    has_position: bool,
+) -> Instruction {
+    let allowlist_entry = allowlisted.then(|| find_allowlist_address(&market.market, &user).0);
+
+    build(
+        swap_accounts(market, user, allowlist_entry, has_position),
+        instruction::TradeToPrice {
+            target_price,
+            is_yes,
+            max_spend,
+        },
+    )
+}
+
+/// Swaps `amount` shares of one outcome for the other. Gated markets only
+/// admit `allowlisted` users here.
+pub fn switch_side(
+    market: &MarketAddresses,
+    user: Pubkey,
+    amount: u64,
+    from_yes: bool,
+    min_out: u64,
+    allowlisted: bool,
+    has_position: bool,
+) -> Instruction {
+    let allowlist_entry = allowlisted.then(|| find_allowlist_address(&market.market, &user).0);
+
+    build(
+        swap_accounts(market, user, allowlist_entry, has_position),
+        instruction::SwitchSide {
+            amount,
+            from_yes,
+            min_out,
+        },
+    )
+}
+
+/// Sells the user's whole `is_yes` holding, merging YES+NO pairs first while
+/// the market is unsettled. Gated markets only admit `allowlisted` users here.
+pub fn close_position(
+    market: &MarketAddresses,
+    user: Pubkey,
+    is_yes: bool,
+    min_out: u64,
+    close_ata: bool,
+    allowlisted: bool,
+    has_position: bool,
+) -> Instruction {
+    let allowlist_entry = allowlisted.then(|| find_allowlist_address(&market.market, &user).0);
+
+    build(
+        swap_accounts(market, user, allowlist_entry, has_position),
+        instruction::ClosePosition {
+            is_yes,
+            min_out,
+            close_ata,
+        },
+    )
+}
+
+pub fn quote_swap(
+    market: &MarketAddresses,
+    is_usdc_to_token: bool,
+    amount: u64,
+    is_yes: bool,
+) -> Instruction {
+    build(
+        accounts::QuoteSwap {
+            mint_yes: market.mint_yes,
+            mint_no: market.mint_no,
+            vault_yes: market.vault_yes,
+            vault_no: market.vault_no,
+            market: market.market,
+            token_program: market.token_program,
+        },
+        instruction::QuoteSwap {
+            is_usdc_to_token,
+            amount,
+            is_yes,
+        },
+    )
+}
+
+/// Resolving needs `winner` (`Some(true)` for YES). `mark_outcomes` also
+/// renames both outcome tokens to WON/LOST. The Metaplex accounts are ignored
+/// for Token-2022 metadata.
+pub fn settle(
+    market: &MarketAddresses,
+    authority: Pubkey,
+    is_resolved: bool,
+    winner: Option<bool>,
+    mark_outcomes: bool,
+) -> Instruction {
+    build(
+        accounts::SettleMarket {
+            authority,
+            market: market.market,
+            mint_yes: market.mint_yes,
+            mint_no: market.mint_no,
+            metadata_yes: Some(market.metadata_yes),
+            metadata_no: Some(market.metadata_no),
+            token_metadata_program: Some(mpl_token_metadata::ID),
+            token_program: market.token_program,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::Settle {
+            is_resolved,
+            winner,
+            mark_outcomes,
+        },
+    )
+}
+
+/// Freezes `token_account`, which must hold the settled market's losing
+/// outcome.
+pub fn freeze_losing_account(
+    market: &MarketAddresses,
+    authority: Pubkey,
+    mint: Pubkey,
+    token_account: Pubkey,
+) -> Instruction {
+    build(
+        accounts::FreezeLosingAccount {
+            authority,
+            market: market.market,
+            mint,
+            token_account,
+            token_program: market.token_program,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::FreezeLosingAccount,
+    )
+}
+
+pub fn claim(
+    market: &MarketAddresses,
+    user: Pubkey,
+    is_yes: bool,
+    has_position: bool,
+) -> Instruction {
+    let (user_ata_usdc, unwrap_account) = market.collateral_accounts(&user);
+
+    build(
+        accounts::ClaimReward {
+            user,
+            mint_yes: market.mint_yes,
+            mint_no: market.mint_no,
+            mint_usdc: market.mint_usdc,
+            vault_yes: market.vault_yes,
+            vault_no: market.vault_no,
+            vault_usdc: market.vault_usdc,
+            user_ata_yes: market.user_ata_yes(&user),
+            user_ata_no: market.user_ata_no(&user),
+            user_ata_usdc,
+            unwrap_account,
+            market: market.market,
+            position: has_position.then(|| market.position(&user)),
+            token_program: market.token_program,
+            collateral_token_program: market.collateral_token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::Claim { is_yes },
+    )
+}
+
+/// One market's winning side for [`claim_many`].
+#[derive(Clone, Debug)]
+pub struct ClaimTarget {
+    pub market: MarketAddresses,
+    pub is_yes: bool,
+    /// Whether the user opened a position in this market.
+    pub has_position: bool,
+}
+
+/// Claims `user`'s winning positions in one transaction. All markets must
+/// share `mint_usdc` and token programs with the first one. When `payer` is
+/// not `user`, the market must be delegate of each outcome account and
+/// wrapped SOL is paid to `user`'s collateral account.
+pub fn claim_many(targets: &[ClaimTarget], payer: Pubkey, user: Pubkey) -> Result<Instruction> {
+    let Some(ClaimTarget { market: first, .. }) = targets.first() else {
+        return err!(MarketError::InvalidRemainingAccounts);
+    };
+    let (user_ata_usdc, unwrap_account) = match payer == user {
+        true => first.collateral_accounts(&user),
+        false => (Some(first.user_ata_usdc(&user)), None),
+    };
+
+    let mut ix = build(
+        accounts::ClaimMany {
+            payer,
+            user,
+            mint_usdc: first.mint_usdc,
+            user_ata_usdc,
+            unwrap_account,
+            token_program: first.token_program,
+            collateral_token_program: first.collateral_token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::ClaimMany,
+    );
+
+    for target in targets {
+        let market = &target.market;
+        let (mint, vault) = match target.is_yes {
+            true => (market.mint_yes, market.vault_yes),
+            false => (market.mint_no, market.vault_no),
+        };
+        // The program ID stands in for a missing position.
+        let position = match target.has_position {
+            true => market.position(&user),
+            false => ID,
+        };
+        ix.accounts.extend([
+            AccountMeta::new_readonly(market.market, false),
+            AccountMeta::new(mint, false),
+            AccountMeta::new_readonly(vault, false),
+            AccountMeta::new(market.vault_usdc, false),
+            AccountMeta::new(market.user_ata(&user, &mint), false),
+            AccountMeta::new(position, false),
+        ]);
+    }
+
+    Ok(ix)
+}
+
+pub fn approve_crank(
+    market: &MarketAddresses,
+    user: Pubkey,
+    is_yes: bool,
+    amount: u64,
+) -> Instruction {
+    let mint = if is_yes {
+        market.mint_yes
+    } else {
+        market.mint_no
+    };
+    build(
+        accounts::ApproveCrank {
+            user,
+            market: market.market,
+            mint,
+            user_ata: market.user_ata(&user, &mint),
+            token_program: market.token_program,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::ApproveCrank { amount },
+    )
+}
+
+/// Claims for `holder` as `keeper`. Leave `has_position` unset for a holder
+/// who never opened a position.
+pub fn crank_claim(
+    market: &MarketAddresses,
+    keeper: Pubkey,
+    holder: Pubkey,
+    is_yes: bool,
+    has_position: bool,
+) -> Instruction {
+    build(
+        accounts::CrankClaim {
+            keeper,
+            holder,
+            mint_yes: market.mint_yes,
+            mint_no: market.mint_no,
+            mint_usdc: market.mint_usdc,
+            vault_yes: market.vault_yes,
+            vault_no: market.vault_no,
+            vault_usdc: market.vault_usdc,
+            holder_ata_yes: is_yes.then(|| market.user_ata_yes(&holder)),
+            holder_ata_no: (!is_yes).then(|| market.user_ata_no(&holder)),
+            holder_ata_usdc: market.user_ata_usdc(&holder),
+            keeper_ata_usdc: market.user_ata_usdc(&keeper),
+            market: market.market,
+            holder_position: has_position.then(|| market.position(&holder)),
+            token_program: market.token_program,
+            collateral_token_program: market.collateral_token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::CrankClaim { is_yes },
+    )
+}
+
+pub fn close_market(
+    market: &MarketAddresses,
+    signer: Pubkey,
+    payer: Pubkey,
+    treasury: Pubkey,
+    authority: Pubkey,
+) -> Instruction {
+    build(
+        accounts::CloseMarket {
+            signer,
+            payer,
+            treasury,
+            authority,
+            mint_yes: market.mint_yes,
+            mint_no: market.mint_no,
+            mint_usdc: market.mint_usdc,
+            vault_yes: market.vault_yes,
+            vault_no: market.vault_no,
+            vault_usdc: market.vault_usdc,
+            treasury_ata_usdc: market.user_ata_usdc(&treasury),
+            market: market.market,
+            price_history: market.price_history,
+            market_info: market.market_info,
+            token_program: market.token_program,
+            collateral_token_program: market.collateral_token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::CloseMarket,
+    )
+}
+
+pub fn get_price(market: &MarketAddresses, twap_window: i64) -> Instruction {
+    build(
+        accounts::GetPrice {
+            mint_yes: market.mint_yes,
+            mint_no: market.mint_no,
+            vault_yes: market.vault_yes,
+            vault_no: market.vault_no,
+            market: market.market,
+            price_history: market.price_history,
+            token_program: market.token_program,
+        },
+        instruction::GetPrice { twap_window },
+    )
+}
+
+pub fn lock(market: &MarketAddresses, authority: Pubkey) -> Instruction {
+    build(
+        accounts::Update {
+            authority,
+            market: market.market,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::Lock,
+    )
+}
+
+pub fn unlock(market: &MarketAddresses, authority: Pubkey) -> Instruction {
+    build(
+        accounts::Update {
+            authority,
+            market: market.market,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::Unlock,
+    )
+}
+
+pub fn set_access_root(
+    market: &MarketAddresses,
+    authority: Pubkey,
+    access_root: Option<[u8; 32]>,
+) -> Instruction {
+    build(
+        accounts::SetAccessRoot {
+            authority,
+            market: market.market,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::SetAccessRoot { access_root },
+    )
+}
+
+pub fn grant_access(market: &MarketAddresses, authority: Pubkey, user: Pubkey) -> Instruction {
+    build(
+        accounts::GrantAccess {
+            authority,
+            market: market.market,
+            allowlist_entry: find_allowlist_address(&market.market, &user).0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::GrantAccess { user },
+    )
+}
+
+pub fn revoke_access(market: &MarketAddresses, authority: Pubkey, user: Pubkey) -> Instruction {
+    build(
+        accounts::RevokeAccess {
+            authority,
+            market: market.market,
+            allowlist_entry: find_allowlist_address(&market.market, &user).0,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::RevokeAccess { user },
+    )
+}
+
+/// Creates or replaces the market's [`MarketInfo`](prediction_market_amm::states::MarketInfo).
+/// Only allowed before the market's first swap or deposit.
+pub fn set_market_info(
+    market: &MarketAddresses,
+    authority: Pubkey,
+    info: MarketInfoArgs,
+) -> Instruction {
+    build(
+        accounts::SetMarketInfo {
+            authority,
+            market: market.market,
+            market_info: market.market_info,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        instruction::SetMarketInfo { info },
+    )
+}
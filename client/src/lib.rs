@@ -0,0 +1,8 @@
+pub mod access;
+pub mod instructions;
+pub mod pda;
+pub mod quote;
+pub mod state;
+
+pub use pda::MarketAddresses;
+pub use prediction_market_amm::{self as program, ID as PROGRAM_ID};
@@ -0,0 +1,174 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id, metadata::mpl_token_metadata,
+    token, token_2022,
+};
+use prediction_market_amm::{
+    helpers::{is_native_mint, UNWRAP_SEED},
+    states::{Market, OutcomeMetadata},
+    ID,
+};
+
+pub fn find_market_address(seed: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"market", seed.to_le_bytes().as_ref()], &ID)
+}
+
+pub fn find_yes_mint_address(seed: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"yes_mint", seed.to_le_bytes().as_ref()], &ID)
+}
+
+pub fn find_no_mint_address(seed: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"no_mint", seed.to_le_bytes().as_ref()], &ID)
+}
+
+pub fn find_price_history_address(market: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"price_history", market.as_ref()], &ID)
+}
+
+pub fn find_market_info_address(market: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"market_info", market.as_ref()], &ID)
+}
+
+pub fn find_position_address(market: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"position", market.as_ref(), user.as_ref()], &ID)
+}
+
+pub fn find_allowlist_address(market: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"allowlist", market.as_ref(), user.as_ref()], &ID)
+}
+
+pub fn find_unwrap_address(user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[UNWRAP_SEED, user.as_ref()], &ID)
+}
+
+pub fn find_event_authority_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"__event_authority"], &ID)
+}
+
+pub fn find_metadata_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"metadata", mpl_token_metadata::ID.as_ref(), mint.as_ref()],
+        &mpl_token_metadata::ID,
+    )
+}
+
+/// Every address a market's instructions touch, derived from its seed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarketAddresses {
+    pub seed: u64,
+    pub market: Pubkey,
+    pub mint_yes: Pubkey,
+    pub mint_no: Pubkey,
+    pub mint_usdc: Pubkey,
+    pub vault_yes: Pubkey,
+    pub vault_no: Pubkey,
+    pub vault_usdc: Pubkey,
+    pub price_history: Pubkey,
+    pub market_info: Pubkey,
+    pub metadata_yes: Pubkey,
+    pub metadata_no: Pubkey,
+    /// Owns the outcome mints and their vaults.
+    pub token_program: Pubkey,
+    /// Owns `mint_usdc`, `vault_usdc` and every collateral account.
+    pub collateral_token_program: Pubkey,
+}
+
+impl MarketAddresses {
+    pub fn new(seed: u64, mint_usdc: Pubkey) -> Self {
+        Self::with_token_program(seed, mint_usdc, token::ID)
+    }
+
+    /// Outcome mints and collateral share `token_program`.
+    pub fn with_token_program(seed: u64, mint_usdc: Pubkey, token_program: Pubkey) -> Self {
+        Self::with_token_programs(seed, mint_usdc, token_program, token_program)
+    }
+
+    pub fn with_token_programs(
+        seed: u64,
+        mint_usdc: Pubkey,
+        token_program: Pubkey,
+        collateral_token_program: Pubkey,
+    ) -> Self {
+        let market = find_market_address(seed).0;
+        let mint_yes = find_yes_mint_address(seed).0;
+        let mint_no = find_no_mint_address(seed).0;
+
+        Self {
+            seed,
+            market,
+            mint_yes,
+            mint_no,
+            mint_usdc,
+            vault_yes: get_associated_token_address_with_program_id(
+                &market,
+                &mint_yes,
+                &token_program,
+            ),
+            vault_no: get_associated_token_address_with_program_id(
+                &market,
+                &mint_no,
+                &token_program,
+            ),
+            vault_usdc: get_associated_token_address_with_program_id(
+                &market,
+                &mint_usdc,
+                &collateral_token_program,
+            ),
+            price_history: find_price_history_address(&market).0,
+            market_info: find_market_info_address(&market).0,
+            metadata_yes: find_metadata_address(&mint_yes).0,
+            metadata_no: find_metadata_address(&mint_no).0,
+            token_program,
+            collateral_token_program,
+        }
+    }
+
+    /// `collateral_token_program` is the owner of the market's `mint_usdc`,
+    /// which the market account does not record.
+    pub fn from_market(market: &Market, collateral_token_program: Pubkey) -> Self {
+        let token_program = match market.outcome_metadata {
+            OutcomeMetadata::Metaplex => token::ID,
+            OutcomeMetadata::TokenExtensions => token_2022::ID,
+        };
+        Self::with_token_programs(
+            market.seed,
+            market.mint_usdc,
+            token_program,
+            collateral_token_program,
+        )
+    }
+
+    pub fn user_ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+
+    pub fn user_ata_yes(&self, owner: &Pubkey) -> Pubkey {
+        self.user_ata(owner, &self.mint_yes)
+    }
+
+    pub fn user_ata_no(&self, owner: &Pubkey) -> Pubkey {
+        self.user_ata(owner, &self.mint_no)
+    }
+
+    pub fn user_ata_usdc(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(
+            owner,
+            &self.mint_usdc,
+            &self.collateral_token_program,
+        )
+    }
+
+    /// `(user_ata_usdc, unwrap_account)` for instructions paying collateral
+    /// to `owner`. Wrapped SOL markets skip the collateral account and pay
+    /// out as lamports through the temporary unwrap account.
+    pub fn collateral_accounts(&self, owner: &Pubkey) -> (Option<Pubkey>, Option<Pubkey>) {
+        match is_native_mint(&self.mint_usdc) {
+            true => (None, Some(find_unwrap_address(owner).0)),
+            false => (Some(self.user_ata_usdc(owner)), None),
+        }
+    }
+
+    pub fn position(&self, user: &Pubkey) -> Pubkey {
+        find_position_address(&self.market, user).0
+    }
+}
@@ -0,0 +1,33 @@
+use anchor_lang::Result;
+use prediction_market_amm::{
+    helpers::{price_to_u64, quote_lmsr_swap, LMSRCalculator, SwapQuote},
+    states::Market,
+};
+
+/// Same breakdown `swap` and `quote_swap` compute on-chain for these vault
+/// balances.
+pub fn quote_swap(
+    market: &Market,
+    vault_yes: u64,
+    vault_no: u64,
+    is_usdc_to_token: bool,
+    amount: u64,
+    is_yes: bool,
+) -> Result<SwapQuote> {
+    quote_lmsr_swap(
+        market.liquidity()?,
+        market.fee,
+        amount,
+        vault_yes,
+        vault_no,
+        is_usdc_to_token,
+        is_yes,
+    )
+}
+
+/// Spot price of one outcome, scaled by 10^PRECISION.
+pub fn spot_price(market: &Market, vault_yes: u64, vault_no: u64, is_yes: bool) -> Result<u64> {
+    price_to_u64(
+        LMSRCalculator::new(market.liquidity()?, vault_yes, vault_no).calculate_price(is_yes)?,
+    )
+}
@@ -0,0 +1,18 @@
+use anchor_lang::{AccountDeserialize, Result};
+use prediction_market_amm::states::{Market, MarketInfo, Position, PriceHistory};
+
+pub fn decode_market(data: &[u8]) -> Result<Market> {
+    Market::try_deserialize(&mut &data[..])
+}
+
+pub fn decode_price_history(data: &[u8]) -> Result<PriceHistory> {
+    PriceHistory::try_deserialize(&mut &data[..])
+}
+
+pub fn decode_market_info(data: &[u8]) -> Result<MarketInfo> {
+    MarketInfo::try_deserialize(&mut &data[..])
+}
+
+pub fn decode_position(data: &[u8]) -> Result<Position> {
+    Position::try_deserialize(&mut &data[..])
+}
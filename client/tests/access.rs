@@ -0,0 +1,102 @@
+mod common;
+
+use common::{assert_error, Test, UNIT};
+use prediction_market_amm::error::MarketError;
+use prediction_market_client::{
+    access,
+    instructions::{self, Access},
+    MarketAddresses,
+};
+use solana_sdk::{
+    instruction::Instruction,
+    signature::{Keypair, Signer},
+};
+
+fn buy(market: &MarketAddresses, user: &Keypair, expiration: i64, access: Access) -> Instruction {
+    instructions::swap(
+        market,
+        user.pubkey(),
+        true,
+        10 * UNIT,
+        true,
+        1,
+        expiration,
+        access,
+        None,
+        false,
+    )
+}
+
+#[tokio::test]
+async fn gated_markets_admit_proofs_and_allowlist_entries() {
+    let mut test = Test::start().await;
+    let market = test.market(1).await;
+    let payer = test.payer();
+    let member = test.user(100 * UNIT).await;
+    let invited = test.user(100 * UNIT).await;
+    let outsider = test.user(100 * UNIT).await;
+    let expiration = test.now().await + 60;
+
+    let members = [member.pubkey(), payer.pubkey()];
+    test.send(
+        &[instructions::set_access_root(
+            &market,
+            payer.pubkey(),
+            access::merkle_root(&members),
+        )],
+        &[&payer],
+    )
+    .await
+    .unwrap();
+
+    let proof = access::merkle_proof(&members, &member.pubkey()).unwrap();
+    test.send(
+        &[buy(&market, &member, expiration, Access::Proof(proof))],
+        &[&member],
+    )
+    .await
+    .unwrap();
+    assert_error(
+        test.send(
+            &[buy(&market, &outsider, expiration, Access::Open)],
+            &[&outsider],
+        )
+        .await,
+        MarketError::AccessDenied,
+    );
+
+    test.send(
+        &[instructions::grant_access(
+            &market,
+            payer.pubkey(),
+            invited.pubkey(),
+        )],
+        &[&payer],
+    )
+    .await
+    .unwrap();
+    test.send(
+        &[buy(&market, &invited, expiration, Access::Allowlisted)],
+        &[&invited],
+    )
+    .await
+    .unwrap();
+
+    test.send(
+        &[instructions::revoke_access(
+            &market,
+            payer.pubkey(),
+            invited.pubkey(),
+        )],
+        &[&payer],
+    )
+    .await
+    .unwrap();
+    assert!(test
+        .send(
+            &[buy(&market, &invited, expiration, Access::Allowlisted)],
+            &[&invited],
+        )
+        .await
+        .is_err());
+}
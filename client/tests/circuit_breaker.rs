@@ -0,0 +1,87 @@
+mod common;
+
+use common::{assert_error, Test, UNIT};
+use prediction_market_amm::{error::MarketError, states::CircuitBreaker};
+use prediction_market_client::{
+    instructions::{self, Access},
+    MarketAddresses,
+};
+use solana_sdk::{
+    instruction::Instruction,
+    signature::{Keypair, Signer},
+};
+
+fn buy_yes(market: &MarketAddresses, user: &Keypair, amount: u64, expiration: i64) -> Instruction {
+    instructions::swap(
+        market,
+        user.pubkey(),
+        true,
+        amount,
+        true,
+        1,
+        expiration,
+        Access::Open,
+        None,
+        false,
+    )
+}
+
+async fn guarded_market(test: &mut Test) -> MarketAddresses {
+    test.market_with(1, test.mint_usdc, |args| {
+        args.circuit_breaker = CircuitBreaker {
+            max_price_move: 50_000,
+            window_slots: 1_000,
+            max_price_impact: 20_000,
+        }
+    })
+    .await
+}
+
+#[tokio::test]
+async fn swaps_that_move_the_price_too_far_are_rejected() {
+    let mut test = Test::start().await;
+    let market = guarded_market(&mut test).await;
+    let user = test.user(1_000 * UNIT).await;
+    let expiration = test.now().await + 60;
+
+    assert_error(
+        test.send(&[buy_yes(&market, &user, 500 * UNIT, expiration)], &[&user])
+            .await,
+        MarketError::CircuitBreakerTripped,
+    );
+    assert!(!test.market_state(&market).await.locked);
+
+    // Each swap stays under the impact cap, but together they leave the
+    // band around the reference price.
+    let mut tripped = false;
+    for amount in 30..40 {
+        let result = test
+            .send(
+                &[buy_yes(&market, &user, amount * UNIT, expiration)],
+                &[&user],
+            )
+            .await;
+        if result.is_err() {
+            assert_error(result, MarketError::CircuitBreakerTripped);
+            tripped = true;
+            break;
+        }
+    }
+    assert!(tripped);
+
+    // Balanced deposits pass the breaker since they leave the price alone.
+    test.send(
+        &[instructions::add_liquidity(
+            &market,
+            user.pubkey(),
+            10 * UNIT,
+            10 * UNIT,
+            expiration,
+            Access::Open,
+            false,
+        )],
+        &[&user],
+    )
+    .await
+    .unwrap();
+}
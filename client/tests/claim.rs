@@ -0,0 +1,100 @@
+mod common;
+
+use common::{assert_error, Test, UNIT};
+use prediction_market_amm::error::MarketError;
+use prediction_market_client::instructions::{self, ClaimTarget};
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn claim_pays_one_unit_per_winning_token() {
+    let mut test = Test::start().await;
+    let market = test.market(1).await;
+    let winner = test.user(1_000 * UNIT).await;
+    let loser = test.user(1_000 * UNIT).await;
+
+    test.buy(&market, &winner, true, 100 * UNIT, false).await;
+    test.buy(&market, &loser, false, 100 * UNIT, false).await;
+    test.settle(&market, true).await;
+
+    let tokens = test.balance(&market.user_ata_yes(&winner.pubkey())).await;
+    let before = test.balance(&market.user_ata_usdc(&winner.pubkey())).await;
+    test.send(
+        &[instructions::claim(&market, winner.pubkey(), true, false)],
+        &[&winner],
+    )
+    .await
+    .unwrap();
+
+    let after = test.balance(&market.user_ata_usdc(&winner.pubkey())).await;
+    assert_eq!(after - before, tokens);
+    assert_eq!(
+        test.balance(&market.user_ata_yes(&winner.pubkey())).await,
+        0
+    );
+
+    assert_error(
+        test.send(
+            &[instructions::claim(&market, loser.pubkey(), false, false)],
+            &[&loser],
+        )
+        .await,
+        MarketError::LosingOutcome,
+    );
+}
+
+#[tokio::test]
+async fn claim_many_pays_markets_with_and_without_a_position() {
+    let mut test = Test::start().await;
+    let with_position = test.market(1).await;
+    let without_position = test.market(2).await;
+    let user = test.user(1_000 * UNIT).await;
+
+    test.send(
+        &[instructions::open_position(&with_position, user.pubkey())],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    test.buy(&with_position, &user, true, 100 * UNIT, true)
+        .await;
+    test.buy(&without_position, &user, false, 50 * UNIT, false)
+        .await;
+    test.settle(&with_position, true).await;
+    test.settle(&without_position, false).await;
+
+    let tokens = test
+        .balance(&with_position.user_ata_yes(&user.pubkey()))
+        .await
+        + test
+            .balance(&without_position.user_ata_no(&user.pubkey()))
+            .await;
+    let before = test
+        .balance(&with_position.user_ata_usdc(&user.pubkey()))
+        .await;
+
+    let claim = instructions::claim_many(
+        &[
+            ClaimTarget {
+                market: with_position,
+                is_yes: true,
+                has_position: true,
+            },
+            ClaimTarget {
+                market: without_position,
+                is_yes: false,
+                has_position: false,
+            },
+        ],
+        user.pubkey(),
+        user.pubkey(),
+    )
+    .unwrap();
+    test.send(&[claim], &[&user]).await.unwrap();
+
+    let after = test
+        .balance(&with_position.user_ata_usdc(&user.pubkey()))
+        .await;
+    assert_eq!(after - before, tokens);
+    let position = test.position(&with_position, &user.pubkey()).await;
+    assert!(position.finalized);
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::{assert_error, Test, UNIT};
+use prediction_market_amm::error::MarketError;
+use prediction_market_client::{instructions, MarketAddresses};
+use solana_sdk::{instruction::Instruction, signature::Signer};
+
+fn close(test: &Test, market: &MarketAddresses) -> Instruction {
+    let payer = test.payer().pubkey();
+    instructions::close_market(market, payer, payer, payer, payer)
+}
+
+#[tokio::test]
+async fn close_market_waits_for_every_claim() {
+    let mut test = Test::start().await;
+    let market = test.market(1).await;
+    let user = test.user(1_000 * UNIT).await;
+    let payer = test.payer();
+
+    test.buy(&market, &user, true, 100 * UNIT, false).await;
+    test.settle(&market, true).await;
+
+    assert_error(
+        test.send(&[close(&test, &market)], &[&payer]).await,
+        MarketError::UnclaimedWinnings,
+    );
+
+    test.send(
+        &[instructions::claim(&market, user.pubkey(), true, false)],
+        &[&user],
+    )
+    .await
+    .unwrap();
+
+    // The unspent subsidy and the fees go to the treasury.
+    let remainder = test.balance(&market.vault_usdc).await;
+    assert!(remainder > 0);
+    let treasury_ata = market.user_ata_usdc(&payer.pubkey());
+    let before = test.balance(&treasury_ata).await;
+    test.send(&[close(&test, &market)], &[&payer])
+        .await
+        .unwrap();
+
+    assert_eq!(test.balance(&treasury_ata).await - before, remainder);
+    for account in [
+        market.market,
+        market.vault_yes,
+        market.vault_no,
+        market.vault_usdc,
+    ] {
+        assert!(!test.exists(&account).await);
+    }
+}
+
+#[tokio::test]
+async fn close_market_sweeps_unclaimed_winnings_after_the_claim_window() {
+    let mut test = Test::start().await;
+    let market = test
+        .market_with(1, test.mint_usdc, |args| args.claim_window = 3_600)
+        .await;
+    let user = test.user(1_000 * UNIT).await;
+    let payer = test.payer();
+
+    test.buy(&market, &user, true, 100 * UNIT, false).await;
+    test.settle(&market, true).await;
+    let settled_at = test.market_state(&market).await.settled_at;
+
+    test.warp_to(settled_at + 3_599).await;
+    assert_error(
+        test.send(&[close(&test, &market)], &[&payer]).await,
+        MarketError::UnclaimedWinnings,
+    );
+
+    test.warp_to(settled_at + 3_600).await;
+    let remainder = test.balance(&market.vault_usdc).await;
+    let treasury_ata = market.user_ata_usdc(&payer.pubkey());
+    let before = test.balance(&treasury_ata).await;
+    test.send(&[close(&test, &market)], &[&payer])
+        .await
+        .unwrap();
+
+    assert_eq!(test.balance(&treasury_ata).await - before, remainder);
+    assert!(!test.exists(&market.market).await);
+    assert!(test
+        .send(
+            &[instructions::claim(&market, user.pubkey(), true, false)],
+            &[&user],
+        )
+        .await
+        .is_err());
+}
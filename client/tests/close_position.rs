@@ -0,0 +1,97 @@
+mod common;
+
+use common::{assert_error, Test, UNIT};
+use prediction_market_amm::error::MarketError;
+use prediction_market_client::instructions;
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn close_position_merges_pairs_and_sells_the_rest() {
+    let mut test = Test::start().await;
+    let market = test.market(1).await;
+    let user = test.user(1_000 * UNIT).await;
+
+    test.buy(&market, &user, true, 100 * UNIT, false).await;
+    test.buy(&market, &user, false, 20 * UNIT, false).await;
+    let before = test.balance(&market.user_ata_usdc(&user.pubkey())).await;
+
+    test.send(
+        &[instructions::close_position(
+            &market,
+            user.pubkey(),
+            true,
+            1,
+            true,
+            false,
+            false,
+        )],
+        &[&user],
+    )
+    .await
+    .unwrap();
+
+    assert!(test.balance(&market.user_ata_usdc(&user.pubkey())).await > before);
+    assert!(!test.exists(&market.user_ata_yes(&user.pubkey())).await);
+    assert_eq!(test.balance(&market.user_ata_no(&user.pubkey())).await, 0);
+}
+
+#[tokio::test]
+async fn settled_markets_only_pay_out_through_claims() {
+    let mut test = Test::start().await;
+    let market = test.market(1).await;
+    let user = test.user(1_000 * UNIT).await;
+
+    test.buy(&market, &user, true, 100 * UNIT, false).await;
+    test.buy(&market, &user, false, 100 * UNIT, false).await;
+    test.settle(&market, true).await;
+
+    // Selling the losing side into the vault would drain the winners' collateral.
+    assert_error(
+        test.send(
+            &[instructions::close_position(
+                &market,
+                user.pubkey(),
+                false,
+                1,
+                false,
+                false,
+                false,
+            )],
+            &[&user],
+        )
+        .await,
+        MarketError::MarketSettled,
+    );
+    assert_error(
+        test.send(
+            &[instructions::switch_side(
+                &market,
+                user.pubkey(),
+                UNIT,
+                false,
+                1,
+                false,
+                false,
+            )],
+            &[&user],
+        )
+        .await,
+        MarketError::MarketSettled,
+    );
+    assert_error(
+        test.send(
+            &[instructions::trade_to_price(
+                &market,
+                user.pubkey(),
+                100_000,
+                false,
+                u64::MAX,
+                false,
+                false,
+            )],
+            &[&user],
+        )
+        .await,
+        MarketError::MarketSettled,
+    );
+}
@@ -0,0 +1,416 @@
+//! Runs the program natively under `solana-program-test`. Markets keep their
+//! outcome metadata on Token-2022 mints, so no Metaplex binary is needed.
+
+#![allow(dead_code)]
+
+use anchor_lang::{
+    prelude::{AccountInfo, Clock, Pubkey},
+    solana_program::{entrypoint::ProgramResult, instruction::Instruction, program_pack::Pack},
+    AccountDeserialize, AnchorDeserialize,
+};
+use anchor_spl::{
+    associated_token::{
+        get_associated_token_address_with_program_id,
+        spl_associated_token_account::instruction::create_associated_token_account_idempotent,
+    },
+    token::spl_token,
+    token_2022,
+    token_interface::{
+        spl_token_2022::{
+            self,
+            extension::{BaseStateWithExtensions, StateWithExtensions},
+            state::Mint as SplMint,
+        },
+        spl_token_metadata_interface::state::TokenMetadata,
+    },
+};
+use prediction_market_amm::{
+    error::MarketError,
+    states::{CircuitBreaker, Market, MarketLimits, OutcomeMetadata, Position},
+};
+use prediction_market_client::{
+    instructions::{self, Access, InitializeArgs},
+    MarketAddresses, PROGRAM_ID,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+};
+
+pub const DECIMALS: u8 = 6;
+pub const UNIT: u64 = 1_000_000;
+/// Collateral the creator holds to fund market subsidies.
+const CREATOR_COLLATERAL: u64 = 1_000_000 * UNIT;
+const USER_LAMPORTS: u64 = 100_000_000_000;
+
+fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    // The entrypoint wants accounts that outlive the call.
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    prediction_market_amm::entry(program_id, accounts, data)
+}
+
+pub struct Test {
+    pub ctx: ProgramTestContext,
+    /// SPL Token collateral with [`DECIMALS`], minted by the payer.
+    pub mint_usdc: Pubkey,
+}
+
+impl Test {
+    pub async fn start() -> Self {
+        let mut program_test =
+            ProgramTest::new("prediction_market_amm", PROGRAM_ID, processor!(process));
+        program_test.prefer_bpf(false);
+        program_test.set_compute_max_units(1_400_000);
+
+        let ctx = program_test.start_with_context().await;
+        let mut test = Self {
+            ctx,
+            mint_usdc: Pubkey::default(),
+        };
+
+        test.mint_usdc = test.create_mint(&spl_token::ID).await;
+        let payer = test.payer();
+        test.fund_collateral(&payer.pubkey(), CREATOR_COLLATERAL)
+            .await;
+        test
+    }
+
+    pub fn payer(&self) -> Keypair {
+        self.ctx.payer.insecure_clone()
+    }
+
+    /// Sends `instructions` paid for by the first signer.
+    pub async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let blockhash = self.ctx.get_new_latest_blockhash().await?;
+        let tx = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signers[0].pubkey()),
+            signers,
+            blockhash,
+        );
+        self.ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// Return data of a view instruction, simulated with the payer as fee payer.
+    pub async fn view<T: AnchorDeserialize>(&mut self, instruction: Instruction) -> T {
+        let payer = self.payer();
+        let blockhash = self.ctx.get_new_latest_blockhash().await.unwrap();
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&payer.pubkey()),
+            &[&payer],
+            blockhash,
+        );
+        let simulation = self
+            .ctx
+            .banks_client
+            .simulate_transaction(tx)
+            .await
+            .unwrap();
+        simulation.result.unwrap().unwrap();
+
+        let return_data = simulation
+            .simulation_details
+            .and_then(|details| details.return_data)
+            .expect("no return data");
+        T::try_from_slice(&return_data.data).unwrap()
+    }
+
+    pub async fn create_mint(&mut self, token_program: &Pubkey) -> Pubkey {
+        let payer = self.payer();
+        let mint = Keypair::new();
+        let rent = self.ctx.banks_client.get_rent().await.unwrap();
+
+        self.send(
+            &[
+                system_instruction::create_account(
+                    &payer.pubkey(),
+                    &mint.pubkey(),
+                    rent.minimum_balance(spl_token::state::Mint::LEN),
+                    spl_token::state::Mint::LEN as u64,
+                    token_program,
+                ),
+                // Token-2022 builds this for either program.
+                spl_token_2022::instruction::initialize_mint2(
+                    token_program,
+                    &mint.pubkey(),
+                    &payer.pubkey(),
+                    None,
+                    DECIMALS,
+                )
+                .unwrap(),
+            ],
+            &[&payer, &mint],
+        )
+        .await
+        .unwrap();
+        mint.pubkey()
+    }
+
+    /// Mints `amount` of the test collateral to `owner`'s token account.
+    pub async fn fund_collateral(&mut self, owner: &Pubkey, amount: u64) {
+        let payer = self.payer();
+        let ata =
+            get_associated_token_address_with_program_id(owner, &self.mint_usdc, &spl_token::ID);
+
+        self.send(
+            &[
+                create_associated_token_account_idempotent(
+                    &payer.pubkey(),
+                    owner,
+                    &self.mint_usdc,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::mint_to(
+                    &spl_token::ID,
+                    &self.mint_usdc,
+                    &ata,
+                    &payer.pubkey(),
+                    &[],
+                    amount,
+                )
+                .unwrap(),
+            ],
+            &[&payer],
+        )
+        .await
+        .unwrap();
+    }
+
+    /// A wallet with SOL for fees and `collateral` of the test collateral.
+    pub async fn user(&mut self, collateral: u64) -> Keypair {
+        let payer = self.payer();
+        let user = Keypair::new();
+
+        self.send(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &user.pubkey(),
+                USER_LAMPORTS,
+            )],
+            &[&payer],
+        )
+        .await
+        .unwrap();
+        if collateral > 0 {
+            self.fund_collateral(&user.pubkey(), collateral).await;
+        }
+        user
+    }
+
+    /// Opens a Token-2022 market on the test collateral, ending in an hour.
+    pub async fn market(&mut self, seed: u64) -> MarketAddresses {
+        self.market_with(seed, self.mint_usdc, |_| {}).await
+    }
+
+    pub async fn market_with(
+        &mut self,
+        seed: u64,
+        mint_usdc: Pubkey,
+        configure: impl FnOnce(&mut InitializeArgs),
+    ) -> MarketAddresses {
+        let payer = self.payer();
+        let collateral_token_program = self.owner(&mint_usdc).await;
+        let market = MarketAddresses::with_token_programs(
+            seed,
+            mint_usdc,
+            token_2022::ID,
+            collateral_token_program,
+        );
+
+        let mut args = self.initialize_args(seed).await;
+        configure(&mut args);
+
+        self.send(
+            &[instructions::initialize(
+                &market,
+                payer.pubkey(),
+                payer.pubkey(),
+                args,
+            )],
+            &[&payer],
+        )
+        .await
+        .unwrap();
+        market
+    }
+
+    /// A Token-2022 market at 50% with a 1% fee, ending in an hour.
+    pub async fn initialize_args(&mut self, seed: u64) -> InitializeArgs {
+        InitializeArgs {
+            name: format!("market {seed}"),
+            token_yes_name: "YES".to_string(),
+            token_yes_symbol: "YES".to_string(),
+            token_no_name: "NO".to_string(),
+            token_no_symbol: "NO".to_string(),
+            token_yes_uri: String::new(),
+            token_no_uri: String::new(),
+            fee: 100,
+            end_time: self.now().await + 3_600,
+            keeper_tip_bps: 0,
+            limits: MarketLimits::default(),
+            circuit_breaker: CircuitBreaker::default(),
+            initial_price: 500_000,
+            outcome_metadata: OutcomeMetadata::TokenExtensions,
+            observation_interval: 60,
+            claim_window: 0,
+        }
+    }
+
+    pub async fn owner(&mut self, address: &Pubkey) -> Pubkey {
+        self.ctx
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+            .unwrap_or_else(|| panic!("{address} does not exist"))
+            .owner
+    }
+
+    pub async fn exists(&mut self, address: &Pubkey) -> bool {
+        self.ctx
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+            .is_some()
+    }
+
+    pub async fn lamports(&mut self, address: &Pubkey) -> u64 {
+        self.ctx.banks_client.get_balance(*address).await.unwrap()
+    }
+
+    /// Token balance of `account` under either token program; 0 if missing.
+    pub async fn balance(&mut self, account: &Pubkey) -> u64 {
+        match self.ctx.banks_client.get_account(*account).await.unwrap() {
+            // Token-2022 accounts start with the same base layout.
+            Some(account) => {
+                spl_token::state::Account::unpack_from_slice(
+                    &account.data[..spl_token::state::Account::LEN],
+                )
+                .unwrap()
+                .amount
+            }
+            None => 0,
+        }
+    }
+
+    /// Name in a Token-2022 outcome mint's metadata extension.
+    pub async fn token_name(&mut self, mint: &Pubkey) -> String {
+        let account = self
+            .ctx
+            .banks_client
+            .get_account(*mint)
+            .await
+            .unwrap()
+            .unwrap();
+        let state = StateWithExtensions::<SplMint>::unpack(&account.data).unwrap();
+        state
+            .get_variable_len_extension::<TokenMetadata>()
+            .unwrap()
+            .name
+    }
+
+    pub async fn market_state(&mut self, market: &MarketAddresses) -> Market {
+        self.decode(&market.market).await
+    }
+
+    pub async fn position(&mut self, market: &MarketAddresses, user: &Pubkey) -> Position {
+        self.decode(&market.position(user)).await
+    }
+
+    async fn decode<T: AccountDeserialize>(&mut self, address: &Pubkey) -> T {
+        let account = self
+            .ctx
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+            .unwrap_or_else(|| panic!("{address} does not exist"));
+        T::try_deserialize(&mut &account.data[..]).unwrap()
+    }
+
+    pub async fn clock(&mut self) -> Clock {
+        self.ctx.banks_client.get_sysvar::<Clock>().await.unwrap()
+    }
+
+    pub async fn now(&mut self) -> i64 {
+        self.clock().await.unix_timestamp
+    }
+
+    /// Moves the clock to `unix_timestamp` without advancing slots.
+    pub async fn warp_to(&mut self, unix_timestamp: i64) {
+        let clock = Clock {
+            unix_timestamp,
+            ..self.clock().await
+        };
+        self.ctx.set_sysvar(&clock);
+    }
+
+    /// Buys `is_yes` shares with `amount` of collateral at any price.
+    pub async fn buy(
+        &mut self,
+        market: &MarketAddresses,
+        user: &Keypair,
+        is_yes: bool,
+        amount: u64,
+        has_position: bool,
+    ) {
+        let expiration = self.now().await + 60;
+        self.send(
+            &[instructions::swap(
+                market,
+                user.pubkey(),
+                true,
+                amount,
+                is_yes,
+                1,
+                expiration,
+                Access::Open,
+                None,
+                has_position,
+            )],
+            &[user],
+        )
+        .await
+        .unwrap();
+    }
+
+    /// Moves past the market's `end_time` and settles it for `yes_won`.
+    pub async fn settle(&mut self, market: &MarketAddresses, yes_won: bool) {
+        let end_time = self.market_state(market).await.end_time;
+        self.warp_to(end_time + 1).await;
+
+        let payer = self.payer();
+        self.send(
+            &[instructions::settle(
+                market,
+                payer.pubkey(),
+                true,
+                Some(yes_won),
+                false,
+            )],
+            &[&payer],
+        )
+        .await
+        .unwrap();
+    }
+}
+
+/// Asserts that `result` failed with `error`.
+pub fn assert_error(result: Result<(), BanksClientError>, error: MarketError) {
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(code),
+        ))) => assert_eq!(code, u32::from(error), "expected {error:?}"),
+        other => panic!("expected {error:?}, got {other:?}"),
+    }
+}
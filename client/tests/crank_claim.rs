@@ -0,0 +1,52 @@
+mod common;
+
+use common::{assert_error, Test, UNIT};
+use prediction_market_amm::error::MarketError;
+use prediction_market_client::instructions;
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn keeper_claims_for_a_holder_without_a_position() {
+    let mut test = Test::start().await;
+    let market = test
+        .market_with(1, test.mint_usdc, |args| args.keeper_tip_bps = 50)
+        .await;
+    let holder = test.user(1_000 * UNIT).await;
+    let keeper = test.user(0).await;
+
+    test.buy(&market, &holder, true, 100 * UNIT, false).await;
+    test.settle(&market, true).await;
+
+    let holder_ata = market.user_ata_yes(&holder.pubkey());
+    let tokens = test.balance(&holder_ata).await;
+    let crank = instructions::crank_claim(&market, keeper.pubkey(), holder.pubkey(), true, false);
+
+    // Nothing happens until the holder delegates to the market.
+    assert_error(
+        test.send(std::slice::from_ref(&crank), &[&keeper]).await,
+        MarketError::NotDelegated,
+    );
+
+    test.send(
+        &[instructions::approve_crank(
+            &market,
+            holder.pubkey(),
+            true,
+            tokens,
+        )],
+        &[&holder],
+    )
+    .await
+    .unwrap();
+    let before = test.balance(&market.user_ata_usdc(&holder.pubkey())).await;
+    test.send(&[crank], &[&keeper]).await.unwrap();
+
+    let tip = tokens * 50 / 10_000;
+    let after = test.balance(&market.user_ata_usdc(&holder.pubkey())).await;
+    assert_eq!(after - before, tokens - tip);
+    assert_eq!(
+        test.balance(&market.user_ata_usdc(&keeper.pubkey())).await,
+        tip
+    );
+    assert_eq!(test.balance(&holder_ata).await, 0);
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use common::{assert_error, Test, UNIT};
+use prediction_market_amm::error::MarketError;
+use prediction_market_client::{
+    instructions::{self, Access},
+    MarketAddresses,
+};
+use solana_sdk::{instruction::Instruction, signature::Signer};
+
+fn deposit(
+    market: &MarketAddresses,
+    user: &impl Signer,
+    yes: u64,
+    no: u64,
+    expiration: i64,
+    has_position: bool,
+) -> Instruction {
+    instructions::add_liquidity(
+        market,
+        user.pubkey(),
+        yes,
+        no,
+        expiration,
+        Access::Open,
+        has_position,
+    )
+}
+
+#[tokio::test]
+async fn deposits_mint_balanced_pairs_at_one_unit_each() {
+    let mut test = Test::start().await;
+    let market = test.market(1).await;
+    let user = test.user(1_000 * UNIT).await;
+    let expiration = test.now().await + 60;
+
+    assert_error(
+        test.send(
+            &[deposit(
+                &market,
+                &user,
+                100 * UNIT,
+                50 * UNIT,
+                expiration,
+                false,
+            )],
+            &[&user],
+        )
+        .await,
+        MarketError::UnbalancedDeposit,
+    );
+
+    let vaults = [market.vault_yes, market.vault_no, market.vault_usdc];
+    let mut before = Vec::new();
+    for vault in &vaults {
+        before.push(test.balance(vault).await);
+    }
+    test.send(
+        &[deposit(
+            &market,
+            &user,
+            100 * UNIT,
+            100 * UNIT,
+            expiration,
+            false,
+        )],
+        &[&user],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        test.balance(&market.user_ata_usdc(&user.pubkey())).await,
+        900 * UNIT
+    );
+    for (vault, before) in vaults.iter().zip(before) {
+        assert_eq!(test.balance(vault).await - before, 100 * UNIT);
+    }
+}
+
+#[tokio::test]
+async fn deposits_count_towards_the_exposure_cap() {
+    let mut test = Test::start().await;
+    let market = test
+        .market_with(1, test.mint_usdc, |args| {
+            args.limits.max_user_exposure = 150 * UNIT
+        })
+        .await;
+    let user = test.user(1_000 * UNIT).await;
+    let expiration = test.now().await + 60;
+
+    assert_error(
+        test.send(
+            &[deposit(
+                &market,
+                &user,
+                100 * UNIT,
+                100 * UNIT,
+                expiration,
+                false,
+            )],
+            &[&user],
+        )
+        .await,
+        MarketError::PositionRequired,
+    );
+
+    test.send(
+        &[
+            instructions::open_position(&market, user.pubkey()),
+            deposit(&market, &user, 100 * UNIT, 100 * UNIT, expiration, true),
+        ],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    let position = test.position(&market, &user.pubkey()).await;
+    assert_eq!(position.liquidity_added, 100 * UNIT);
+
+    assert_error(
+        test.send(
+            &[deposit(
+                &market,
+                &user,
+                100 * UNIT,
+                100 * UNIT,
+                expiration,
+                true,
+            )],
+            &[&user],
+        )
+        .await,
+        MarketError::ExposureLimitExceeded,
+    );
+}
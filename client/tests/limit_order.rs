@@ -0,0 +1,79 @@
+mod common;
+
+use common::{assert_error, Test, UNIT};
+use prediction_market_amm::{error::MarketError, helpers::LimitOrder};
+use prediction_market_client::{
+    instructions::{self, Access},
+    MarketAddresses,
+};
+use solana_sdk::{
+    instruction::Instruction,
+    signature::{Keypair, Signer},
+};
+
+const LIMIT: u64 = 550_000;
+
+fn buy_yes(
+    market: &MarketAddresses,
+    user: &Keypair,
+    amount: u64,
+    expiration: i64,
+    partial_fill: bool,
+) -> Instruction {
+    instructions::swap(
+        market,
+        user.pubkey(),
+        true,
+        amount,
+        true,
+        1,
+        expiration,
+        Access::Open,
+        Some(LimitOrder {
+            price: LIMIT,
+            partial_fill,
+        }),
+        false,
+    )
+}
+
+#[tokio::test]
+async fn limit_orders_fill_only_within_the_price() {
+    let mut test = Test::start().await;
+    let market = test.market(1).await;
+    let user = test.user(1_000 * UNIT).await;
+    let expiration = test.now().await + 60;
+
+    assert_error(
+        test.send(
+            &[buy_yes(&market, &user, 500 * UNIT, expiration, false)],
+            &[&user],
+        )
+        .await,
+        MarketError::LimitPriceExceeded,
+    );
+
+    let collateral = market.user_ata_usdc(&user.pubkey());
+    test.send(
+        &[buy_yes(&market, &user, 500 * UNIT, expiration, true)],
+        &[&user],
+    )
+    .await
+    .unwrap();
+
+    // The fee counts towards the average price.
+    let spent = 1_000 * UNIT - test.balance(&collateral).await;
+    let shares = test.balance(&market.user_ata_yes(&user.pubkey())).await;
+    assert!(spent > 0 && spent < 500 * UNIT);
+    assert!(spent as u128 * UNIT as u128 <= LIMIT as u128 * shares as u128);
+
+    // The price now sits at the limit, so nothing more fills.
+    assert_error(
+        test.send(
+            &[buy_yes(&market, &user, 10 * UNIT, expiration, true)],
+            &[&user],
+        )
+        .await,
+        MarketError::LimitPriceExceeded,
+    );
+}
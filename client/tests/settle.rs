@@ -0,0 +1,129 @@
+mod common;
+
+use common::{assert_error, Test, UNIT};
+use prediction_market_amm::error::MarketError;
+use prediction_market_client::instructions::{self, Access};
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn trading_closes_at_end_time() {
+    let mut test = Test::start().await;
+    let market = test.market(1).await;
+    let user = test.user(1_000 * UNIT).await;
+    let payer = test.payer();
+
+    let buy = |expiration| {
+        instructions::swap(
+            &market,
+            user.pubkey(),
+            true,
+            10 * UNIT,
+            true,
+            1,
+            expiration,
+            Access::Open,
+            None,
+            false,
+        )
+    };
+    let deposit = |expiration| {
+        instructions::add_liquidity(
+            &market,
+            user.pubkey(),
+            10 * UNIT,
+            10 * UNIT,
+            expiration,
+            Access::Open,
+            false,
+        )
+    };
+
+    let end_time = test.market_state(&market).await.end_time;
+    test.warp_to(end_time).await;
+    test.send(&[buy(end_time + 60)], &[&user]).await.unwrap();
+    assert_error(
+        test.send(
+            &[instructions::settle(
+                &market,
+                payer.pubkey(),
+                true,
+                Some(true),
+                false,
+            )],
+            &[&payer],
+        )
+        .await,
+        MarketError::MarketNotEnded,
+    );
+
+    test.warp_to(end_time + 1).await;
+    assert_error(
+        test.send(&[buy(end_time + 60)], &[&user]).await,
+        MarketError::MarketEnded,
+    );
+    assert_error(
+        test.send(&[deposit(end_time + 60)], &[&user]).await,
+        MarketError::MarketEnded,
+    );
+}
+
+#[tokio::test]
+async fn settling_renames_outcomes_only_when_asked() {
+    let mut test = Test::start().await;
+    let kept = test.market(1).await;
+    let marked = test.market(2).await;
+    let undecided = test.market(3).await;
+    let payer = test.payer();
+
+    test.settle(&kept, true).await;
+    let state = test.market_state(&kept).await;
+    assert!(state.settled);
+    assert_eq!(state.winner, Some(true));
+    assert_eq!(test.token_name(&kept.mint_yes).await, "YES");
+    assert_eq!(test.token_name(&kept.mint_no).await, "NO");
+
+    test.send(
+        &[instructions::settle(
+            &marked,
+            payer.pubkey(),
+            true,
+            Some(false),
+            true,
+        )],
+        &[&payer],
+    )
+    .await
+    .unwrap();
+    assert_eq!(test.token_name(&marked.mint_yes).await, "YES LOST");
+    assert_eq!(test.token_name(&marked.mint_no).await, "NO WON");
+
+    // Resolving without a winner or twice is refused.
+    assert_error(
+        test.send(
+            &[instructions::settle(
+                &undecided,
+                payer.pubkey(),
+                true,
+                None,
+                false,
+            )],
+            &[&payer],
+        )
+        .await,
+        MarketError::WinnerRequired,
+    );
+    assert_error(
+        test.send(
+            &[instructions::settle(
+                &kept,
+                payer.pubkey(),
+                true,
+                Some(false),
+                false,
+            )],
+            &[&payer],
+        )
+        .await,
+        MarketError::MarketSettled,
+    );
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use anchor_spl::{token, token_2022};
+use common::{assert_error, Test, UNIT};
+use prediction_market_amm::{error::MarketError, helpers::SwapQuote};
+use prediction_market_client::{instructions, MarketAddresses};
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn outcome_metadata_lives_on_token_2022_mints() {
+    let mut test = Test::start().await;
+    let market = test.market(1).await;
+
+    for mint in [market.mint_yes, market.mint_no] {
+        assert_eq!(test.owner(&mint).await, token_2022::ID);
+    }
+    assert_eq!(test.token_name(&market.mint_yes).await, "YES");
+    assert_eq!(test.token_name(&market.mint_no).await, "NO");
+}
+
+#[tokio::test]
+async fn quote_swap_matches_the_fill() {
+    let mut test = Test::start().await;
+    let market = test.market(1).await;
+    let user = test.user(1_000 * UNIT).await;
+
+    let quote: SwapQuote = test
+        .view(instructions::quote_swap(&market, true, 100 * UNIT, true))
+        .await;
+    test.buy(&market, &user, true, 100 * UNIT, false).await;
+
+    assert_eq!(
+        test.balance(&market.user_ata_yes(&user.pubkey())).await,
+        quote.amount_out
+    );
+}
+
+#[tokio::test]
+async fn token_extensions_need_the_token_2022_program() {
+    let mut test = Test::start().await;
+    let payer = test.payer();
+    let market = MarketAddresses::with_token_programs(1, test.mint_usdc, token::ID, token::ID);
+
+    // The default arguments ask for metadata extensions on classic mints.
+    let initialize = instructions::initialize(
+        &market,
+        payer.pubkey(),
+        payer.pubkey(),
+        test.initialize_args(1).await,
+    );
+    assert_error(
+        test.send(&[initialize], &[&payer]).await,
+        MarketError::Token2022Required,
+    );
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use anchor_spl::token::spl_token::native_mint;
+use common::Test;
+use prediction_market_client::{
+    instructions::{self, Access},
+    pda::find_unwrap_address,
+    MarketAddresses,
+};
+use solana_sdk::{
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    signature::{Keypair, Signer},
+};
+
+/// Sends with the test payer covering fees, so `user`'s lamports only move
+/// with the trade.
+async fn send_as(test: &mut Test, instruction: Instruction, user: &Keypair) {
+    let payer = test.payer();
+    test.send(&[instruction], &[&payer, user]).await.unwrap();
+}
+
+async fn wrapped_sol_market(test: &mut Test) -> (MarketAddresses, Keypair) {
+    let market = test.market_with(1, native_mint::ID, |_| {}).await;
+    let user = test.user(0).await;
+    (market, user)
+}
+
+#[tokio::test]
+async fn wrapped_sol_trades_and_claims_in_lamports() {
+    let mut test = Test::start().await;
+    let (market, user) = wrapped_sol_market(&mut test).await;
+    let unwrap_account = find_unwrap_address(&user.pubkey()).0;
+    let expiration = test.now().await + 60;
+
+    let start = test.lamports(&user.pubkey()).await;
+    send_as(
+        &mut test,
+        instructions::swap(
+            &market,
+            user.pubkey(),
+            true,
+            LAMPORTS_PER_SOL,
+            true,
+            1,
+            expiration,
+            Access::Open,
+            None,
+            false,
+        ),
+        &user,
+    )
+    .await;
+    let bought = test.lamports(&user.pubkey()).await;
+    // The swap also paid rent for both outcome accounts.
+    let rent = test.lamports(&market.user_ata_yes(&user.pubkey())).await
+        + test.lamports(&market.user_ata_no(&user.pubkey())).await;
+    assert_eq!(start - bought, LAMPORTS_PER_SOL + rent);
+
+    let shares = test.balance(&market.user_ata_yes(&user.pubkey())).await;
+    send_as(
+        &mut test,
+        instructions::swap(
+            &market,
+            user.pubkey(),
+            false,
+            shares / 2,
+            true,
+            1,
+            expiration,
+            Access::Open,
+            None,
+            false,
+        ),
+        &user,
+    )
+    .await;
+    assert!(test.lamports(&user.pubkey()).await > bought);
+    assert!(!test.exists(&unwrap_account).await);
+
+    test.settle(&market, true).await;
+    let tokens = test.balance(&market.user_ata_yes(&user.pubkey())).await;
+    let before = test.lamports(&user.pubkey()).await;
+    send_as(
+        &mut test,
+        instructions::claim(&market, user.pubkey(), true, false),
+        &user,
+    )
+    .await;
+
+    assert_eq!(test.lamports(&user.pubkey()).await - before, tokens);
+    assert!(!test.exists(&unwrap_account).await);
+}
+
+#[tokio::test]
+async fn switch_side_closes_the_unwrap_account() {
+    let mut test = Test::start().await;
+    let (market, user) = wrapped_sol_market(&mut test).await;
+    test.buy(&market, &user, true, LAMPORTS_PER_SOL, false)
+        .await;
+
+    let shares = test.balance(&market.user_ata_yes(&user.pubkey())).await;
+    send_as(
+        &mut test,
+        instructions::switch_side(&market, user.pubkey(), shares, true, 1, false, false),
+        &user,
+    )
+    .await;
+
+    assert_eq!(test.balance(&market.user_ata_yes(&user.pubkey())).await, 0);
+    assert!(test.balance(&market.user_ata_no(&user.pubkey())).await > 0);
+    assert!(!test.exists(&find_unwrap_address(&user.pubkey()).0).await);
+}
@@ -0,0 +1,675 @@
+use std::{path::Path, str::FromStr};
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use solana_sdk::{clock::Slot, signature::Signature};
+
+use crate::decode::{DecodedTransaction, ProgramEvent};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS transactions (
+    signature TEXT PRIMARY KEY,
+    slot INTEGER NOT NULL,
+    block_time INTEGER
+);
+CREATE INDEX IF NOT EXISTS transactions_slot ON transactions (slot);
+
+CREATE TABLE IF NOT EXISTS cursor (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    slot INTEGER NOT NULL,
+    signature TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS markets (
+    market TEXT PRIMARY KEY,
+    seed INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    mint_yes TEXT NOT NULL,
+    mint_no TEXT NOT NULL,
+    mint_usdc TEXT NOT NULL,
+    fee INTEGER NOT NULL,
+    end_time INTEGER NOT NULL,
+    keeper_tip_bps INTEGER NOT NULL,
+    initial_price INTEGER NOT NULL,
+    subsidy INTEGER NOT NULL,
+    collateral_decimals INTEGER NOT NULL,
+    created_slot INTEGER NOT NULL,
+    created_signature TEXT NOT NULL,
+    locked INTEGER NOT NULL DEFAULT 0,
+    settled INTEGER NOT NULL DEFAULT 0,
+    settled_at INTEGER,
+    closed INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS market_updates (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    market TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    dust_swept INTEGER,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE TABLE IF NOT EXISTS liquidity (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    market TEXT NOT NULL,
+    user TEXT NOT NULL,
+    amount_yes INTEGER NOT NULL,
+    amount_no INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE TABLE IF NOT EXISTS trades (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    market TEXT NOT NULL,
+    user TEXT NOT NULL,
+    is_buying INTEGER NOT NULL,
+    is_yes INTEGER NOT NULL,
+    amount_in INTEGER NOT NULL,
+    amount_out INTEGER NOT NULL,
+    fee INTEGER NOT NULL,
+    price_before INTEGER NOT NULL,
+    price_after INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS trades_market_user ON trades (market, user);
+
+CREATE TABLE IF NOT EXISTS switches (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    market TEXT NOT NULL,
+    user TEXT NOT NULL,
+    from_yes INTEGER NOT NULL,
+    amount_in INTEGER NOT NULL,
+    amount_out INTEGER NOT NULL,
+    collateral INTEGER NOT NULL,
+    fee INTEGER NOT NULL,
+    price_before INTEGER NOT NULL,
+    price_after INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE TABLE IF NOT EXISTS merges (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    market TEXT NOT NULL,
+    user TEXT NOT NULL,
+    pairs INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE TABLE IF NOT EXISTS settlements (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    market TEXT NOT NULL,
+    settled INTEGER NOT NULL,
+    settled_at INTEGER NOT NULL,
+    winner INTEGER,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE TABLE IF NOT EXISTS claims (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    market TEXT NOT NULL,
+    user TEXT NOT NULL,
+    is_yes INTEGER NOT NULL,
+    amount_burned INTEGER NOT NULL,
+    payout INTEGER NOT NULL,
+    keeper_tip INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE TABLE IF NOT EXISTS positions (
+    market TEXT NOT NULL,
+    user TEXT NOT NULL,
+    yes_bought INTEGER NOT NULL,
+    yes_sold INTEGER NOT NULL,
+    no_bought INTEGER NOT NULL,
+    no_sold INTEGER NOT NULL,
+    usdc_spent INTEGER NOT NULL,
+    usdc_received INTEGER NOT NULL,
+    fees_paid INTEGER NOT NULL,
+    claimed INTEGER NOT NULL,
+    PRIMARY KEY (market, user)
+);
+
+CREATE TABLE IF NOT EXISTS market_info (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    market TEXT NOT NULL,
+    market_info TEXT NOT NULL,
+    title TEXT NOT NULL,
+    category TEXT NOT NULL,
+    rules_hash TEXT NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS market_info_market ON market_info (market);
+
+CREATE TABLE IF NOT EXISTS access (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    market TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    user TEXT,
+    access_root TEXT,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS access_market ON access (market);
+
+CREATE TABLE IF NOT EXISTS freezes (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    market TEXT NOT NULL,
+    mint TEXT NOT NULL,
+    token_account TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS freezes_market ON freezes (market);
+
+CREATE TABLE IF NOT EXISTS crank_approvals (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    market TEXT NOT NULL,
+    user TEXT NOT NULL,
+    is_yes INTEGER NOT NULL,
+    -- TEXT: approvals of u64::MAX don't fit a SQLite INTEGER.
+    amount TEXT NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS crank_approvals_market ON crank_approvals (market);
+";
+
+/// Tables keyed by transaction that are dropped when rewinding.
+const EVENT_TABLES: [&str; 12] = [
+    "market_updates",
+    "market_info",
+    "access",
+    "liquidity",
+    "trades",
+    "switches",
+    "merges",
+    "settlements",
+    "freezes",
+    "crank_approvals",
+    "claims",
+    "markets",
+];
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    pub fn cursor(&self) -> Result<Option<(Slot, Signature)>> {
+        let cursor = self
+            .conn
+            .query_row(
+                "SELECT slot, signature FROM cursor WHERE id = 1",
+                [],
+                |row| Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?;
+
+        cursor
+            .map(|(slot, signature)| Ok((slot, Signature::from_str(&signature)?)))
+            .transpose()
+    }
+
+    /// Forgets everything indexed at or after `slot` so it is fetched and
+    /// applied again on the next run.
+    pub fn rewind(&mut self, slot: Slot) -> Result<()> {
+        let tx = self.conn.transaction()?;
+
+        for table in EVENT_TABLES {
+            let slot_column = if table == "markets" {
+                "created_slot"
+            } else {
+                "slot"
+            };
+            tx.execute(
+                &format!("DELETE FROM {table} WHERE {slot_column} >= ?1"),
+                params![slot],
+            )?;
+        }
+        tx.execute("DELETE FROM transactions WHERE slot >= ?1", params![slot])?;
+        tx.execute("DELETE FROM cursor", [])?;
+        tx.execute(
+            "INSERT INTO cursor (id, slot, signature)
+             SELECT 1, slot, signature FROM transactions
+             ORDER BY slot DESC, rowid DESC LIMIT 1",
+            [],
+        )?;
+
+        tx.commit()?;
+        self.refresh_projections()
+    }
+
+    /// Records one transaction and its events atomically. Transactions that
+    /// were already indexed are ignored.
+    pub fn apply(&mut self, decoded: &DecodedTransaction) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let signature = decoded.signature.to_string();
+        let slot = decoded.slot;
+        let block_time = decoded.block_time;
+
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO transactions (signature, slot, block_time) VALUES (?1, ?2, ?3)",
+            params![signature, slot, block_time],
+        )?;
+        if inserted == 0 {
+            return Ok(());
+        }
+
+        for (index, event) in decoded.events.iter().enumerate() {
+            match event {
+                ProgramEvent::MarketCreated(event) => {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO markets (market, seed, name, mint_yes, mint_no,
+                         mint_usdc, fee, end_time, keeper_tip_bps, initial_price, subsidy,
+                         collateral_decimals, created_slot, created_signature)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                        params![
+                            event.market.to_string(),
+                            event.seed,
+                            event.market_name,
+                            event.mint_yes.to_string(),
+                            event.mint_no.to_string(),
+                            event.mint_usdc.to_string(),
+                            event.fee,
+                            event.end_time,
+                            event.keeper_tip_bps,
+                            event.initial_price,
+                            event.subsidy,
+                            event.collateral_decimals,
+                            slot,
+                            signature,
+                        ],
+                    )?;
+                }
+                ProgramEvent::LiquidityAdded(event) => {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO liquidity VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                        params![
+                            signature,
+                            index,
+                            slot,
+                            block_time,
+                            event.market.to_string(),
+                            event.user.to_string(),
+                            event.amount_yes,
+                            event.amount_no,
+                        ],
+                    )?;
+                }
+                ProgramEvent::Swapped(event) => {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO trades
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                        params![
+                            signature,
+                            index,
+                            slot,
+                            block_time,
+                            event.market.to_string(),
+                            event.user.to_string(),
+                            event.is_buying,
+                            event.is_yes,
+                            event.amount_in,
+                            event.amount_out,
+                            event.fee,
+                            event.price_before,
+                            event.price_after,
+                        ],
+                    )?;
+                }
+                ProgramEvent::MarketLocked(event) => {
+                    insert_market_update(
+                        &tx,
+                        &signature,
+                        index,
+                        slot,
+                        block_time,
+                        &event.market.to_string(),
+                        "locked",
+                        None,
+                    )?;
+                }
+                ProgramEvent::MarketUnlocked(event) => {
+                    insert_market_update(
+                        &tx,
+                        &signature,
+                        index,
+                        slot,
+                        block_time,
+                        &event.market.to_string(),
+                        "unlocked",
+                        None,
+                    )?;
+                }
+                ProgramEvent::MarketClosed(event) => {
+                    insert_market_update(
+                        &tx,
+                        &signature,
+                        index,
+                        slot,
+                        block_time,
+                        &event.market.to_string(),
+                        "closed",
+                        Some(event.dust_swept),
+                    )?;
+                }
+                ProgramEvent::SideSwitched(event) => {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO switches
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                        params![
+                            signature,
+                            index,
+                            slot,
+                            block_time,
+                            event.market.to_string(),
+                            event.user.to_string(),
+                            event.from_yes,
+                            event.amount_in,
+                            event.amount_out,
+                            event.collateral,
+                            event.fee,
+                            event.price_before,
+                            event.price_after,
+                        ],
+                    )?;
+                }
+                ProgramEvent::PairsMerged(event) => {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO merges VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                        params![
+                            signature,
+                            index,
+                            slot,
+                            block_time,
+                            event.market.to_string(),
+                            event.user.to_string(),
+                            event.pairs,
+                        ],
+                    )?;
+                }
+                ProgramEvent::MarketSettled(event) => {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO settlements VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                        params![
+                            signature,
+                            index,
+                            slot,
+                            block_time,
+                            event.market.to_string(),
+                            event.settled,
+                            event.settled_at,
+                            event.winner,
+                        ],
+                    )?;
+                }
+                ProgramEvent::MarketInfoUpdated(event) => {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO market_info VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                        params![
+                            signature,
+                            index,
+                            slot,
+                            block_time,
+                            event.market.to_string(),
+                            event.market_info.to_string(),
+                            event.title,
+                            format!("{:?}", event.category),
+                            hex(&event.rules_hash),
+                        ],
+                    )?;
+                }
+                ProgramEvent::AccessRootUpdated(event) => {
+                    insert_access(
+                        &tx,
+                        &signature,
+                        index,
+                        slot,
+                        block_time,
+                        &event.market.to_string(),
+                        "root",
+                        None,
+                        event.access_root.as_ref().map(hex),
+                    )?;
+                }
+                ProgramEvent::AccessGranted(event) => {
+                    insert_access(
+                        &tx,
+                        &signature,
+                        index,
+                        slot,
+                        block_time,
+                        &event.market.to_string(),
+                        "granted",
+                        Some(event.user.to_string()),
+                        None,
+                    )?;
+                }
+                ProgramEvent::AccessRevoked(event) => {
+                    insert_access(
+                        &tx,
+                        &signature,
+                        index,
+                        slot,
+                        block_time,
+                        &event.market.to_string(),
+                        "revoked",
+                        Some(event.user.to_string()),
+                        None,
+                    )?;
+                }
+                ProgramEvent::LosingAccountFrozen(event) => {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO freezes VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                        params![
+                            signature,
+                            index,
+                            slot,
+                            block_time,
+                            event.market.to_string(),
+                            event.mint.to_string(),
+                            event.token_account.to_string(),
+                            event.owner.to_string(),
+                        ],
+                    )?;
+                }
+                ProgramEvent::CrankApproved(event) => {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO crank_approvals VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                        params![
+                            signature,
+                            index,
+                            slot,
+                            block_time,
+                            event.market.to_string(),
+                            event.user.to_string(),
+                            event.is_yes,
+                            event.amount.to_string(),
+                        ],
+                    )?;
+                }
+                ProgramEvent::Claimed(event) => {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO claims
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                        params![
+                            signature,
+                            index,
+                            slot,
+                            block_time,
+                            event.market.to_string(),
+                            event.user.to_string(),
+                            event.is_yes,
+                            event.amount_burned,
+                            event.payout,
+                            event.keeper_tip,
+                        ],
+                    )?;
+                }
+            }
+        }
+
+        tx.execute(
+            "INSERT INTO cursor (id, slot, signature) VALUES (1, ?1, ?2)
+             ON CONFLICT (id) DO UPDATE SET slot = excluded.slot, signature = excluded.signature",
+            params![slot, signature],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Recomputes market status and per-user positions from the event tables.
+    pub fn refresh_projections(&mut self) -> Result<()> {
+        let tx = self.conn.transaction()?;
+
+        tx.execute_batch(
+            "UPDATE markets SET
+                locked = COALESCE((
+                    SELECT u.kind = 'locked' FROM market_updates u
+                    WHERE u.market = markets.market AND u.kind IN ('locked', 'unlocked')
+                    ORDER BY u.slot DESC, u.rowid DESC LIMIT 1
+                ), 0),
+                settled = COALESCE((
+                    SELECT s.settled FROM settlements s
+                    WHERE s.market = markets.market
+                    ORDER BY s.slot DESC, s.rowid DESC LIMIT 1
+                ), 0),
+                settled_at = (
+                    SELECT s.settled_at FROM settlements s
+                    WHERE s.market = markets.market AND s.settled = 1
+                    ORDER BY s.slot DESC, s.rowid DESC LIMIT 1
+                ),
+                closed = EXISTS (
+                    SELECT 1 FROM market_updates u
+                    WHERE u.market = markets.market AND u.kind = 'closed'
+                );
+
+            DELETE FROM positions;
+
+            INSERT INTO positions
+            SELECT
+                market,
+                user,
+                SUM(CASE WHEN is_buying AND is_yes THEN amount_out ELSE 0 END),
+                SUM(CASE WHEN NOT is_buying AND is_yes THEN amount_in ELSE 0 END),
+                SUM(CASE WHEN is_buying AND NOT is_yes THEN amount_out ELSE 0 END),
+                SUM(CASE WHEN NOT is_buying AND NOT is_yes THEN amount_in ELSE 0 END),
+                SUM(CASE WHEN is_buying THEN amount_in ELSE 0 END),
+                SUM(CASE WHEN NOT is_buying THEN amount_out ELSE 0 END),
+                SUM(fee),
+                0
+            FROM (
+                SELECT market, user, is_buying, is_yes, amount_in, amount_out, fee FROM trades
+                -- A switch is booked as a sell of one side and a buy of the other.
+                UNION ALL
+                SELECT market, user, 0, from_yes, amount_in, collateral, fee FROM switches
+                UNION ALL
+                SELECT market, user, 1, NOT from_yes, collateral, amount_out, 0 FROM switches
+                -- Merged pairs are sold back at one unit of collateral each.
+                UNION ALL
+                SELECT market, user, 0, 1, pairs, pairs, 0 FROM merges
+                UNION ALL
+                SELECT market, user, 0, 0, pairs, 0, 0 FROM merges
+            )
+            GROUP BY market, user;
+
+            INSERT INTO positions
+            SELECT market, user, 0, 0, 0, 0, 0, 0, 0, SUM(payout - keeper_tip)
+            FROM claims
+            WHERE true
+            GROUP BY market, user
+            ON CONFLICT (market, user) DO UPDATE SET claimed = excluded.claimed;",
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn insert_market_update(
+    tx: &rusqlite::Transaction,
+    signature: &str,
+    index: usize,
+    slot: Slot,
+    block_time: Option<i64>,
+    market: &str,
+    kind: &str,
+    dust_swept: Option<u64>,
+) -> Result<()> {
+    tx.execute(
+        "INSERT OR IGNORE INTO market_updates VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![signature, index, slot, block_time, market, kind, dust_swept],
+    )?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn insert_access(
+    tx: &rusqlite::Transaction,
+    signature: &str,
+    index: usize,
+    slot: Slot,
+    block_time: Option<i64>,
+    market: &str,
+    kind: &str,
+    user: Option<String>,
+    access_root: Option<String>,
+) -> Result<()> {
+    tx.execute(
+        "INSERT OR IGNORE INTO access VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            signature,
+            index,
+            slot,
+            block_time,
+            market,
+            kind,
+            user,
+            access_root
+        ],
+    )?;
+    Ok(())
+}
+
+fn hex(bytes: &[u8; 32]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
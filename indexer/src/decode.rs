@@ -0,0 +1,136 @@
+use std::str::FromStr;
+
+use anchor_lang::{event::EVENT_IX_TAG_LE, AnchorDeserialize, Discriminator};
+use anyhow::{Context, Result};
+use prediction_market_client::{program::events::*, PROGRAM_ID};
+use solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta, UiInstruction,
+};
+
+pub enum ProgramEvent {
+    MarketCreated(MarketCreated),
+    LiquidityAdded(LiquidityAdded),
+    Swapped(Swapped),
+    MarketLocked(MarketLocked),
+    MarketUnlocked(MarketUnlocked),
+    MarketSettled(MarketSettled),
+    Claimed(Claimed),
+    MarketClosed(MarketClosed),
+    SideSwitched(SideSwitched),
+    PairsMerged(PairsMerged),
+    MarketInfoUpdated(MarketInfoUpdated),
+    AccessRootUpdated(AccessRootUpdated),
+    AccessGranted(AccessGranted),
+    AccessRevoked(AccessRevoked),
+    LosingAccountFrozen(LosingAccountFrozen),
+    CrankApproved(CrankApproved),
+}
+
+/// Events of one successful transaction, in execution order.
+pub struct DecodedTransaction {
+    pub signature: Signature,
+    pub slot: Slot,
+    pub block_time: Option<i64>,
+    pub events: Vec<ProgramEvent>,
+}
+
+/// Decodes an `emit_cpi!` payload: the event discriminator followed by the
+/// borsh-encoded event. Unknown discriminators are skipped.
+pub fn decode_event(data: &[u8]) -> Result<Option<ProgramEvent>> {
+    if data.len() < 8 {
+        return Ok(None);
+    }
+    let (discriminator, mut payload) = data.split_at(8);
+
+    macro_rules! decode {
+        ($($event:ident),*) => {
+            $(
+                if discriminator == $event::DISCRIMINATOR {
+                    return Ok(Some(ProgramEvent::$event($event::deserialize(&mut payload)?)));
+                }
+            )*
+        };
+    }
+
+    decode!(
+        MarketCreated,
+        LiquidityAdded,
+        Swapped,
+        MarketLocked,
+        MarketUnlocked,
+        MarketSettled,
+        Claimed,
+        MarketClosed,
+        SideSwitched,
+        PairsMerged,
+        MarketInfoUpdated,
+        AccessRootUpdated,
+        AccessGranted,
+        AccessRevoked,
+        LosingAccountFrozen,
+        CrankApproved
+    );
+
+    Ok(None)
+}
+
+pub fn decode_transaction(
+    signature: Signature,
+    tx: EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<DecodedTransaction> {
+    let mut decoded = DecodedTransaction {
+        signature,
+        slot: tx.slot,
+        block_time: tx.block_time,
+        events: Vec::new(),
+    };
+
+    let meta = tx
+        .transaction
+        .meta
+        .context("transaction without status meta")?;
+    if meta.err.is_some() {
+        return Ok(decoded);
+    }
+
+    let versioned = tx
+        .transaction
+        .transaction
+        .decode()
+        .context("undecodable transaction")?;
+
+    let mut account_keys = versioned.message.static_account_keys().to_vec();
+    if let OptionSerializer::Some(loaded) = &meta.loaded_addresses {
+        for key in loaded.writable.iter().chain(loaded.readonly.iter()) {
+            account_keys.push(Pubkey::from_str(key)?);
+        }
+    }
+
+    let inner_instructions = match meta.inner_instructions {
+        OptionSerializer::Some(inner_instructions) => inner_instructions,
+        _ => return Ok(decoded),
+    };
+
+    for instruction in inner_instructions
+        .into_iter()
+        .flat_map(|inner| inner.instructions)
+    {
+        let UiInstruction::Compiled(instruction) = instruction else {
+            continue;
+        };
+
+        if account_keys.get(instruction.program_id_index as usize) != Some(&PROGRAM_ID) {
+            continue;
+        }
+
+        let data = bs58::decode(&instruction.data).into_vec()?;
+        if let Some(payload) = data.strip_prefix(&EVENT_IX_TAG_LE) {
+            if let Some(event) = decode_event(payload)? {
+                decoded.events.push(event);
+            }
+        }
+    }
+
+    Ok(decoded)
+}
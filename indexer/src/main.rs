@@ -0,0 +1,86 @@
+mod db;
+mod decode;
+mod source;
+
+use std::{path::PathBuf, thread, time::Duration};
+
+use anyhow::Result;
+use clap::Parser;
+use solana_sdk::clock::Slot;
+
+use crate::{
+    db::Store,
+    decode::decode_transaction,
+    source::{RpcSource, Source},
+};
+
+#[derive(Parser)]
+#[command(about = "Index prediction_market_amm events into SQLite")]
+struct Cli {
+    #[arg(long, env = "INDEXER_DB", default_value = "prediction_market.sqlite")]
+    db: PathBuf,
+    #[arg(long, env = "RPC_URL", default_value = "http://127.0.0.1:8899")]
+    url: String,
+    /// Read from a local validator ledger instead of RPC.
+    #[cfg(feature = "ledger")]
+    #[arg(long)]
+    ledger: Option<PathBuf>,
+    /// Drop everything indexed at or after this slot and reprocess it.
+    #[arg(long)]
+    from_slot: Option<Slot>,
+    /// Keep polling for new transactions.
+    #[arg(long)]
+    follow: bool,
+    /// Seconds between polls when following.
+    #[arg(long, default_value_t = 10)]
+    poll_interval: u64,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut store = Store::open(&cli.db)?;
+
+    if let Some(slot) = cli.from_slot {
+        store.rewind(slot)?;
+    }
+
+    let source = open_source(&cli)?;
+
+    loop {
+        let indexed = sync(source.as_ref(), &mut store)?;
+        if indexed > 0 {
+            println!("indexed {indexed} transactions");
+        }
+
+        if !cli.follow {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(cli.poll_interval));
+    }
+}
+
+fn open_source(cli: &Cli) -> Result<Box<dyn Source>> {
+    #[cfg(feature = "ledger")]
+    if let Some(path) = &cli.ledger {
+        return Ok(Box::new(source::LedgerSource::open(path)?));
+    }
+
+    Ok(Box::new(RpcSource::new(cli.url.clone())))
+}
+
+/// Applies every finalized transaction after the cursor, then refreshes the
+/// derived market status and positions.
+fn sync(source: &dyn Source, store: &mut Store) -> Result<usize> {
+    let until = store.cursor()?.map(|(_, signature)| signature);
+    let signatures = source.signatures_since(until)?;
+
+    for (signature, _) in &signatures {
+        let tx = source.transaction(signature)?;
+        store.apply(&decode_transaction(*signature, tx)?)?;
+    }
+
+    if !signatures.is_empty() {
+        store.refresh_projections()?;
+    }
+    Ok(signatures.len())
+}
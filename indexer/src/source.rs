@@ -0,0 +1,144 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use prediction_market_client::PROGRAM_ID;
+use solana_client::{
+    rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+    rpc_config::RpcTransactionConfig,
+};
+use solana_sdk::{clock::Slot, commitment_config::CommitmentConfig, signature::Signature};
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding};
+
+const PAGE_SIZE: usize = 1_000;
+
+/// Where finalized program transactions are read from.
+pub trait Source {
+    /// Signatures of program transactions after `until`, oldest first.
+    fn signatures_since(&self, until: Option<Signature>) -> Result<Vec<(Signature, Slot)>>;
+
+    fn transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta>;
+}
+
+pub struct RpcSource {
+    rpc: RpcClient,
+}
+
+impl RpcSource {
+    pub fn new(url: String) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(url, CommitmentConfig::finalized()),
+        }
+    }
+}
+
+impl Source for RpcSource {
+    fn signatures_since(&self, until: Option<Signature>) -> Result<Vec<(Signature, Slot)>> {
+        let mut signatures = Vec::new();
+        let mut before = None;
+
+        loop {
+            let page = self.rpc.get_signatures_for_address_with_config(
+                &PROGRAM_ID,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(PAGE_SIZE),
+                    commitment: Some(CommitmentConfig::finalized()),
+                },
+            )?;
+
+            let done = page.len() < PAGE_SIZE;
+            for status in page {
+                let signature = Signature::from_str(&status.signature)?;
+                before = Some(signature);
+                signatures.push((signature, status.slot));
+            }
+
+            if done {
+                break;
+            }
+        }
+
+        signatures.reverse();
+        Ok(signatures)
+    }
+
+    fn transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+        self.rpc
+            .get_transaction_with_config(
+                signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(CommitmentConfig::finalized()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .with_context(|| format!("fetching transaction {signature}"))
+    }
+}
+
+/// Reads rooted transactions straight from a validator ledger directory,
+/// e.g. `.anchor/test-ledger`.
+#[cfg(feature = "ledger")]
+pub struct LedgerSource {
+    blockstore: solana_ledger::blockstore::Blockstore,
+}
+
+#[cfg(feature = "ledger")]
+impl LedgerSource {
+    pub fn open(path: &std::path::Path) -> Result<Self> {
+        Ok(Self {
+            blockstore: solana_ledger::blockstore::Blockstore::open(path)
+                .with_context(|| format!("opening ledger {}", path.display()))?,
+        })
+    }
+}
+
+#[cfg(feature = "ledger")]
+impl Source for LedgerSource {
+    fn signatures_since(&self, until: Option<Signature>) -> Result<Vec<(Signature, Slot)>> {
+        let highest_slot = self.blockstore.max_root();
+        let mut signatures = Vec::new();
+        let mut before = None;
+
+        loop {
+            let page = self.blockstore.get_confirmed_signatures_for_address2(
+                PROGRAM_ID,
+                highest_slot,
+                before,
+                until,
+                PAGE_SIZE,
+            )?;
+
+            let done = page.infos.len() < PAGE_SIZE;
+            for status in page.infos {
+                before = Some(status.signature);
+                signatures.push((status.signature, status.slot));
+            }
+
+            if done {
+                break;
+            }
+        }
+
+        signatures.reverse();
+        Ok(signatures)
+    }
+
+    fn transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+        self.blockstore
+            .get_rooted_transaction(*signature)?
+            .with_context(|| format!("transaction {signature} not rooted in ledger"))?
+            .encode(UiTransactionEncoding::Base64, Some(0))
+            .with_context(|| format!("encoding transaction {signature}"))
+    }
+}
@@ -0,0 +1,104 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    events::{AccessGranted, AccessRevoked, AccessRootUpdated},
+    states::{AllowlistEntry, Market},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetAccessRoot<'info> {
+    authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"market", market.seed.to_le_bytes().as_ref()],
+        bump = market.market_bump,
+    )]
+    market: Box<Account<'info, Market>>,
+}
+
+impl<'info> SetAccessRoot<'info> {
+    pub fn set_access_root(&mut self, access_root: Option<[u8; 32]>) -> Result<AccessRootUpdated> {
+        self.market.access_root = access_root;
+        Ok(AccessRootUpdated {
+            market: self.market.key(),
+            access_root,
+        })
+    }
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct GrantAccess<'info> {
+    #[account(mut)]
+    authority: Signer<'info>,
+    #[account(
+        has_one = authority,
+        seeds = [b"market", market.seed.to_le_bytes().as_ref()],
+        bump = market.market_bump,
+    )]
+    market: Box<Account<'info, Market>>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AllowlistEntry::INIT_SPACE,
+        seeds = [b"allowlist", market.key().as_ref(), user.as_ref()],
+        bump,
+    )]
+    allowlist_entry: Account<'info, AllowlistEntry>,
+
+    system_program: Program<'info, System>,
+}
+
+impl<'info> GrantAccess<'info> {
+    pub fn grant_access(
+        &mut self,
+        user: Pubkey,
+        bumps: &GrantAccessBumps,
+    ) -> Result<AccessGranted> {
+        self.allowlist_entry.set_inner(AllowlistEntry {
+            market: self.market.key(),
+            user,
+            authority: self.authority.key(),
+            bump: bumps.allowlist_entry,
+        });
+
+        Ok(AccessGranted {
+            market: self.market.key(),
+            user,
+        })
+    }
+}
+
+/// Removes an allowlist entry. Wallets admitted through `access_root` are
+/// removed by rotating the root instead. Works after `close_market` too, so
+/// entries of closed markets can be reclaimed.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct RevokeAccess<'info> {
+    #[account(mut)]
+    authority: Signer<'info>,
+    /// CHECK: Matched by `has_one` on the entry; may already be closed.
+    market: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        close = authority,
+        has_one = market,
+        has_one = authority,
+        seeds = [b"allowlist", market.key().as_ref(), user.as_ref()],
+        bump = allowlist_entry.bump,
+    )]
+    allowlist_entry: Account<'info, AllowlistEntry>,
+}
+
+impl<'info> RevokeAccess<'info> {
+    pub fn revoke_access(&mut self, user: Pubkey) -> Result<AccessRevoked> {
+        Ok(AccessRevoked {
+            market: self.market.key(),
+            user,
+        })
+    }
+}
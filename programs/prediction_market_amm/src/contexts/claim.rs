@@ -6,8 +6,15 @@ use anchor_spl::{
     },
 };
 
-use crate::{assert_not_locked, error::MarketError, states::Market};
+use crate::{
+    assert_not_locked,
+    error::MarketError,
+    events::Claimed,
+    helpers::{claim_payout, payout_account, unwrap_sol, UNWRAP_SEED},
+    states::{Market, Position},
+};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct ClaimReward<'info> {
     #[account(mut)]
@@ -15,101 +22,158 @@ pub struct ClaimReward<'info> {
     #[account(
         mut,
         mint::token_program = token_program,
-        mint::authority = user
+        mint::authority = market
     )]
     mint_yes: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         mint::token_program = token_program,
-        mint::authority = user
+        mint::authority = market
     )]
     mint_no: Box<InterfaceAccount<'info, Mint>>,
     #[account(
-        mint::token_program = token_program,
+        mint::token_program = collateral_token_program,
     )]
     mint_usdc: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         associated_token::mint = mint_yes,
         associated_token::authority = market,
+        associated_token::token_program = token_program,
     )]
     vault_yes: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_no,
         associated_token::authority = market,
+        associated_token::token_program = token_program,
     )]
     vault_no: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_usdc,
-        associated_token::authority = market
+        associated_token::authority = market,
+        associated_token::token_program = collateral_token_program,
     )]
     vault_usdc: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_yes,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
     user_ata_yes: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_no,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
     user_ata_no: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_usdc,
         associated_token::authority = user,
+        associated_token::token_program = collateral_token_program,
+    )]
+    user_ata_usdc: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// Pays wrapped SOL out as lamports; see `Swap::unwrap_account`.
+    #[account(
+        init,
+        payer = user,
+        seeds = [UNWRAP_SEED, user.key().as_ref()],
+        bump,
+        token::mint = mint_usdc,
+        token::authority = user,
+        token::token_program = collateral_token_program,
     )]
-    user_ata_usdc: Box<InterfaceAccount<'info, TokenAccount>>,
+    unwrap_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     #[account(
         mut,
         has_one = mint_yes,
         has_one = mint_no,
+        has_one = mint_usdc,
         seeds = [b"market", market.seed.to_le_bytes().as_ref()],
         bump = market.market_bump,
     )]
     pub market: Box<Account<'info, Market>>,
+    #[account(
+        mut,
+        has_one = market,
+        has_one = user,
+        seeds = [b"position", market.key().as_ref(), user.key().as_ref()],
+        bump = position.bump,
+    )]
+    pub position: Option<Box<Account<'info, Position>>>,
 
     pub token_program: Interface<'info, TokenInterface>,
+    pub collateral_token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> ClaimReward<'info> {
-    pub fn claim(&mut self, is_yes: bool) -> Result<()> {
+    pub fn claim(&mut self, is_yes: bool) -> Result<Claimed> {
         assert_not_locked!(self.market.locked);
 
-        require!(!self.market.settled, MarketError::MarketNotSettled);
+        require!(self.market.settled, MarketError::MarketNotSettled);
+        require!(
+            self.market.winner == Some(is_yes),
+            MarketError::LosingOutcome
+        );
 
-        let (user_tokens, total_tokens) = if is_yes {
-            (self.user_ata_yes.amount, self.mint_yes.supply)
+        let (user_tokens, supply, vault_tokens) = if is_yes {
+            (
+                self.user_ata_yes.amount,
+                self.mint_yes.supply,
+                self.vault_yes.amount,
+            )
         } else {
-            (self.user_ata_no.amount, self.mint_no.supply)
+            (
+                self.user_ata_no.amount,
+                self.mint_no.supply,
+                self.vault_no.amount,
+            )
         };
 
-        require!(user_tokens > 0, MarketError::InsufficientBalance);
-
-        let total_payout = self.vault_usdc.amount;
-
-        let user_payout = (user_tokens as u128)
-            .checked_mul(total_payout as u128)
-            .unwrap()
-            .checked_div(total_tokens as u128)
-            .unwrap() as u64;
+        let user_payout = claim_payout(user_tokens, self.vault_usdc.amount, supply, vault_tokens)?;
 
         self.transfer_amount(user_payout)?;
 
-        self.burn_tokens(user_tokens, is_yes)
+        self.burn_tokens(user_tokens, is_yes)?;
+
+        if let Some(unwrap_account) = &self.unwrap_account {
+            unwrap_sol(
+                unwrap_account.to_account_info(),
+                self.user.to_account_info(),
+                self.collateral_token_program.to_account_info(),
+            )?;
+        }
+
+        if let Some(position) = self.position.as_mut() {
+            position.finalize(user_payout)?;
+        }
+
+        Ok(Claimed {
+            market: self.market.key(),
+            user: self.user.key(),
+            is_yes,
+            amount_burned: user_tokens,
+            payout: user_payout,
+            keeper_tip: 0,
+        })
     }
 
     pub fn transfer_amount(&self, amount: u64) -> Result<()> {
         let accounts = TransferChecked {
             from: self.vault_usdc.to_account_info(),
             mint: self.mint_usdc.to_account_info(),
-            to: self.user_ata_usdc.to_account_info(),
+            to: payout_account(
+                &self.mint_usdc.key(),
+                self.unwrap_account.as_ref().map(|a| a.to_account_info()),
+                self.user_ata_usdc.as_ref().map(|a| a.to_account_info()),
+            )?,
             authority: self.market.to_account_info(),
         };
 
@@ -121,7 +185,7 @@ impl<'info> ClaimReward<'info> {
         let signer_seeds = &[&seeds[..]];
 
         let ctx = CpiContext::new_with_signer(
-            self.token_program.to_account_info(),
+            self.collateral_token_program.to_account_info(),
             accounts,
             signer_seeds,
         );
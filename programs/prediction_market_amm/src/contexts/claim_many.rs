@@ -0,0 +1,257 @@
+use anchor_lang::{prelude::*, solana_program::program_option::COption};
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{
+        burn, transfer_checked, Burn, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
+};
+
+use crate::{
+    assert_not_locked,
+    error::MarketError,
+    events::Claimed,
+    helpers::{claim_payout, payout_account, unwrap_sol, UNWRAP_SEED},
+    states::{Market, Position},
+};
+
+/// Accounts expected per market in `remaining_accounts`:
+/// `[market, winning mint, market's winning vault, vault_usdc, user ATA,
+/// user position]`. Pass the program ID for a user without a position.
+pub const CLAIM_GROUP_LEN: usize = 6;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimMany<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+    /// Anyone can claim for `user`. Without their signature the market must
+    /// be delegate of each outcome account and wrapped SOL stays wrapped.
+    user: SystemAccount<'info>,
+    #[account(
+        mint::token_program = collateral_token_program,
+    )]
+    mint_usdc: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint_usdc,
+        associated_token::authority = user,
+        associated_token::token_program = collateral_token_program
+    )]
+    user_ata_usdc: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// Pays wrapped SOL out as lamports; see `Swap::unwrap_account`.
+    #[account(
+        init,
+        payer = payer,
+        seeds = [UNWRAP_SEED, user.key().as_ref()],
+        bump,
+        token::mint = mint_usdc,
+        token::authority = user,
+        token::token_program = collateral_token_program,
+    )]
+    unwrap_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub collateral_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ClaimMany<'info> {
+    pub fn claim_many(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+    ) -> Result<Vec<Claimed>> {
+        require!(
+            !remaining_accounts.is_empty()
+                && remaining_accounts.len().is_multiple_of(CLAIM_GROUP_LEN),
+            MarketError::InvalidRemainingAccounts
+        );
+
+        let claims = remaining_accounts
+            .chunks(CLAIM_GROUP_LEN)
+            .map(|group| self.claim_group(group))
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Some(unwrap_account) = &self.unwrap_account {
+            require!(self.user.is_signer, ErrorCode::AccountNotSigner);
+            unwrap_sol(
+                unwrap_account.to_account_info(),
+                self.user.to_account_info(),
+                self.collateral_token_program.to_account_info(),
+            )?;
+        }
+
+        Ok(claims)
+    }
+
+    fn claim_group(&self, group: &'info [AccountInfo<'info>]) -> Result<Claimed> {
+        let position = match group[5].key() == crate::ID {
+            true => None,
+            false => Some(Account::<'info, Position>::try_from(&group[5])?),
+        };
+        let market: Account<'info, Market> = Account::try_from(&group[0])?;
+        let mint: InterfaceAccount<'info, Mint> = InterfaceAccount::try_from(&group[1])?;
+        let vault: InterfaceAccount<'info, TokenAccount> = InterfaceAccount::try_from(&group[2])?;
+        let vault_usdc: InterfaceAccount<'info, TokenAccount> =
+            InterfaceAccount::try_from(&group[3])?;
+        let user_ata: InterfaceAccount<'info, TokenAccount> =
+            InterfaceAccount::try_from(&group[4])?;
+
+        // seeds = [b"market", market.seed], bump = market.market_bump
+        let market_key = Pubkey::create_program_address(
+            &[
+                &b"market"[..],
+                &market.seed.to_le_bytes(),
+                &[market.market_bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| MarketError::InvalidMarket)?;
+        require_keys_eq!(market_key, market.key(), MarketError::InvalidMarket);
+
+        assert_not_locked!(market.locked);
+        require!(market.settled, MarketError::MarketNotSettled);
+
+        // has_one = mint_yes / has_one = mint_no
+        let is_yes = if mint.key() == market.mint_yes {
+            true
+        } else if mint.key() == market.mint_no {
+            false
+        } else {
+            return err!(MarketError::InvalidToken);
+        };
+        require!(market.winner == Some(is_yes), MarketError::LosingOutcome);
+        // has_one = mint_usdc
+        require_keys_eq!(
+            market.mint_usdc,
+            self.mint_usdc.key(),
+            MarketError::InvalidToken
+        );
+        require_keys_eq!(
+            *group[1].owner,
+            self.token_program.key(),
+            MarketError::InvalidToken
+        );
+        require!(
+            mint.mint_authority == COption::Some(market.key()),
+            MarketError::InvalidToken
+        );
+
+        require_keys_eq!(
+            vault.key(),
+            get_associated_token_address_with_program_id(
+                &market.key(),
+                &mint.key(),
+                &self.token_program.key(),
+            ),
+            MarketError::InvalidToken
+        );
+        require_keys_eq!(
+            vault_usdc.key(),
+            get_associated_token_address_with_program_id(
+                &market.key(),
+                &self.mint_usdc.key(),
+                &self.collateral_token_program.key(),
+            ),
+            MarketError::InvalidToken
+        );
+        require_keys_eq!(
+            user_ata.key(),
+            get_associated_token_address_with_program_id(
+                &self.user.key(),
+                &mint.key(),
+                &self.token_program.key(),
+            ),
+            MarketError::InvalidToken
+        );
+
+        let user_tokens = user_ata.amount;
+        let user_payout = claim_payout(user_tokens, vault_usdc.amount, mint.supply, vault.amount)?;
+
+        // The market PDA burns for users who did not sign, which needs their
+        // approval as delegate.
+        if !self.user.is_signer {
+            require!(
+                user_ata.delegate == COption::Some(market.key())
+                    && user_ata.delegated_amount >= user_tokens,
+                MarketError::NotDelegated
+            );
+        }
+
+        let seeds = &[
+            &b"market"[..],
+            &market.seed.to_le_bytes(),
+            &[market.market_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let accounts = TransferChecked {
+            from: vault_usdc.to_account_info(),
+            mint: self.mint_usdc.to_account_info(),
+            to: payout_account(
+                &self.mint_usdc.key(),
+                self.unwrap_account.as_ref().map(|a| a.to_account_info()),
+                self.user_ata_usdc.as_ref().map(|a| a.to_account_info()),
+            )?,
+            authority: market.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(
+            self.collateral_token_program.to_account_info(),
+            accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(ctx, user_payout, self.mint_usdc.decimals)?;
+
+        let authority = match self.user.is_signer {
+            true => self.user.to_account_info(),
+            false => market.to_account_info(),
+        };
+
+        let cpi_accounts = Burn {
+            mint: mint.to_account_info(),
+            from: user_ata.to_account_info(),
+            authority,
+        };
+
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        burn(ctx, user_tokens)?;
+
+        // Remaining accounts are not written back by Anchor, so persist the
+        // position here.
+        if let Some(mut position) = position {
+            // seeds = [b"position", market, user], bump = position.bump
+            let position_key = Pubkey::create_program_address(
+                &[
+                    &b"position"[..],
+                    market.key().as_ref(),
+                    self.user.key().as_ref(),
+                    &[position.bump],
+                ],
+                &crate::ID,
+            )
+            .map_err(|_| MarketError::InvalidPosition)?;
+            require_keys_eq!(position_key, position.key(), MarketError::InvalidPosition);
+            require!(group[5].is_writable, ErrorCode::AccountNotMutable);
+
+            position.finalize(user_payout)?;
+            position.exit(&crate::ID)?;
+        }
+
+        Ok(Claimed {
+            market: market.key(),
+            user: self.user.key(),
+            is_yes,
+            amount_burned: user_tokens,
+            payout: user_payout,
+            keeper_tip: 0,
+        })
+    }
+}
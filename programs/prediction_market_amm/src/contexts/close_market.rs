@@ -0,0 +1,291 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        burn, close_account, transfer_checked, Burn, CloseAccount, Mint, TokenAccount,
+        TokenInterface, TransferChecked,
+    },
+};
+
+use crate::{
+    error::MarketError,
+    events::MarketClosed,
+    states::{Market, PriceHistory},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseMarket<'info> {
+    #[account(mut)]
+    signer: Signer<'info>,
+    #[account(mut)]
+    payer: SystemAccount<'info>,
+    treasury: SystemAccount<'info>,
+    #[account(mut)]
+    authority: SystemAccount<'info>,
+    #[account(
+        mut,
+        mint::token_program = token_program,
+        mint::authority = market
+    )]
+    mint_yes: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        mint::token_program = token_program,
+        mint::authority = market
+    )]
+    mint_no: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = collateral_token_program,
+    )]
+    mint_usdc: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_yes,
+        associated_token::authority = market,
+        associated_token::token_program = token_program,
+    )]
+    vault_yes: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_no,
+        associated_token::authority = market,
+        associated_token::token_program = token_program,
+    )]
+    vault_no: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_usdc,
+        associated_token::authority = market,
+        associated_token::token_program = collateral_token_program,
+    )]
+    vault_usdc: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = mint_usdc,
+        associated_token::authority = treasury,
+        associated_token::token_program = collateral_token_program,
+    )]
+    treasury_ata_usdc: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        has_one = mint_yes,
+        has_one = mint_no,
+        has_one = mint_usdc,
+        has_one = payer,
+        has_one = treasury,
+        has_one = authority,
+        close = payer,
+        seeds = [b"market", market.seed.to_le_bytes().as_ref()],
+        bump = market.market_bump,
+    )]
+    pub market: Box<Account<'info, Market>>,
+    #[account(
+        mut,
+        has_one = market,
+        close = payer,
+        seeds = [b"price_history", market.key().as_ref()],
+        bump = price_history.bump,
+    )]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+    /// CHECK: The market's info PDA, closed into `authority` if it was ever
+    /// set. Required rather than optional so it can't be left behind.
+    #[account(
+        mut,
+        seeds = [b"market_info", market.key().as_ref()],
+        bump,
+    )]
+    market_info: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub collateral_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CloseMarket<'info> {
+    /// Closes the market once every winning token has been claimed or the
+    /// market's `claim_window` has run out: the vaults' own outcome tokens
+    /// are burnt, what is left in `vault_usdc` (the unspent subsidy, fees,
+    /// collateral of unsold pairs and anything unclaimed) goes to the
+    /// treasury, and the vaults, market, price history and market info
+    /// return their rent.
+    ///
+    /// The outcome mints and their metadata stay behind. SPL Token mints
+    /// can't be closed at all, Token-2022 mints only with a close authority
+    /// and zero supply, and losing tokens keep that supply above zero since
+    /// nobody has to burn them. Positions and allowlist entries are closed by
+    /// their owners with `close_position_account` and `revoke_access`.
+    pub fn close_market(&mut self) -> Result<MarketClosed> {
+        require!(self.market.settled, MarketError::MarketNotSettled);
+
+        let outstanding_yes = self
+            .mint_yes
+            .supply
+            .checked_sub(self.vault_yes.amount)
+            .ok_or(MarketError::MathUnderflow)?;
+        let outstanding_no = self
+            .mint_no
+            .supply
+            .checked_sub(self.vault_no.amount)
+            .ok_or(MarketError::MathUnderflow)?;
+
+        // Only winning tokens are redeemable, and settling always names the
+        // winner.
+        let unclaimed = match self.market.winner.ok_or(MarketError::WinnerRequired)? {
+            true => outstanding_yes,
+            false => outstanding_no,
+        };
+        require!(
+            unclaimed == 0 || self.claim_window_elapsed(Clock::get()?.unix_timestamp),
+            MarketError::UnclaimedWinnings
+        );
+
+        let dust_swept = self.vault_usdc.amount;
+
+        self.burn_vault(true)?;
+        self.burn_vault(false)?;
+        self.sweep_collateral()?;
+
+        self.close_vault(
+            self.vault_yes.to_account_info(),
+            self.token_program.to_account_info(),
+        )?;
+        self.close_vault(
+            self.vault_no.to_account_info(),
+            self.token_program.to_account_info(),
+        )?;
+        self.close_vault(
+            self.vault_usdc.to_account_info(),
+            self.collateral_token_program.to_account_info(),
+        )?;
+
+        self.close_market_info()?;
+
+        Ok(MarketClosed {
+            market: self.market.key(),
+            dust_swept,
+            rent_recipient: self.payer.key(),
+        })
+    }
+
+    /// Whether the market has a claim window and it has passed.
+    fn claim_window_elapsed(&self, now: i64) -> bool {
+        let window = self.market.claim_window as i64;
+        window > 0 && now >= self.market.settled_at.saturating_add(window)
+    }
+
+    pub fn burn_vault(&self, is_yes: bool) -> Result<()> {
+        let (mint, from, amount) = match is_yes {
+            true => (
+                self.mint_yes.to_account_info(),
+                self.vault_yes.to_account_info(),
+                self.vault_yes.amount,
+            ),
+            false => (
+                self.mint_no.to_account_info(),
+                self.vault_no.to_account_info(),
+                self.vault_no.amount,
+            ),
+        };
+
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let cpi_accounts = Burn {
+            mint,
+            from,
+            authority: self.market.to_account_info(),
+        };
+
+        let seeds = &[
+            &b"market"[..],
+            &self.market.seed.to_le_bytes(),
+            &[self.market.market_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        burn(ctx, amount)
+    }
+
+    pub fn sweep_collateral(&self) -> Result<()> {
+        let amount = self.vault_usdc.amount;
+
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let accounts = TransferChecked {
+            from: self.vault_usdc.to_account_info(),
+            mint: self.mint_usdc.to_account_info(),
+            to: self.treasury_ata_usdc.to_account_info(),
+            authority: self.market.to_account_info(),
+        };
+
+        let seeds = &[
+            &b"market"[..],
+            &self.market.seed.to_le_bytes(),
+            &[self.market.market_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(
+            self.collateral_token_program.to_account_info(),
+            accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(ctx, amount, self.mint_usdc.decimals)
+    }
+
+    /// Closes the market info into `authority`, who paid for it. Only this
+    /// program can have created an account at its address.
+    pub fn close_market_info(&self) -> Result<()> {
+        let info = self.market_info.to_account_info();
+
+        if info.data_is_empty() {
+            return Ok(());
+        }
+
+        let authority = self.authority.to_account_info();
+        **authority.lamports.borrow_mut() = authority
+            .lamports()
+            .checked_add(info.lamports())
+            .ok_or(MarketError::MathOverflow)?;
+        **info.lamports.borrow_mut() = 0;
+
+        info.assign(&System::id());
+        info.realloc(0, false).map_err(Into::into)
+    }
+
+    pub fn close_vault(
+        &self,
+        account: AccountInfo<'info>,
+        token_program: AccountInfo<'info>,
+    ) -> Result<()> {
+        let accounts = CloseAccount {
+            account,
+            destination: self.payer.to_account_info(),
+            authority: self.market.to_account_info(),
+        };
+
+        let seeds = &[
+            &b"market"[..],
+            &self.market.seed.to_le_bytes(),
+            &[self.market.market_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(token_program, accounts, signer_seeds);
+
+        close_account(ctx)
+    }
+}
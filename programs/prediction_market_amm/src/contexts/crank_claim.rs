@@ -0,0 +1,294 @@
+use anchor_lang::{prelude::*, solana_program::program_option::COption};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        approve, burn, transfer_checked, Approve, Burn, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{
+    assert_not_locked,
+    error::MarketError,
+    events::{Claimed, CrankApproved},
+    helpers::{claim_payout, BPS_DENOMINATOR},
+    states::{Market, Position},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CrankClaim<'info> {
+    #[account(mut)]
+    keeper: Signer<'info>,
+    holder: SystemAccount<'info>,
+    #[account(
+        mut,
+        mint::token_program = token_program,
+        mint::authority = market
+    )]
+    mint_yes: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        mint::token_program = token_program,
+        mint::authority = market
+    )]
+    mint_no: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = collateral_token_program,
+    )]
+    mint_usdc: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        associated_token::mint = mint_yes,
+        associated_token::authority = market,
+        associated_token::token_program = token_program,
+    )]
+    vault_yes: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        associated_token::mint = mint_no,
+        associated_token::authority = market,
+        associated_token::token_program = token_program,
+    )]
+    vault_no: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_usdc,
+        associated_token::authority = market,
+        associated_token::token_program = collateral_token_program,
+    )]
+    vault_usdc: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_yes,
+        associated_token::authority = holder,
+        associated_token::token_program = token_program,
+    )]
+    holder_ata_yes: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_no,
+        associated_token::authority = holder,
+        associated_token::token_program = token_program,
+    )]
+    holder_ata_no: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// Wrapped SOL payouts stay wrapped here: only the holder can close it.
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        associated_token::mint = mint_usdc,
+        associated_token::authority = holder,
+        associated_token::token_program = collateral_token_program,
+    )]
+    holder_ata_usdc: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        associated_token::mint = mint_usdc,
+        associated_token::authority = keeper,
+        associated_token::token_program = collateral_token_program,
+    )]
+    keeper_ata_usdc: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        has_one = mint_yes,
+        has_one = mint_no,
+        has_one = mint_usdc,
+        seeds = [b"market", market.seed.to_le_bytes().as_ref()],
+        bump = market.market_bump,
+    )]
+    pub market: Box<Account<'info, Market>>,
+    #[account(
+        mut,
+        has_one = market,
+        constraint = holder_position.user == holder.key() @ MarketError::InvalidPosition,
+        seeds = [b"position", market.key().as_ref(), holder.key().as_ref()],
+        bump = holder_position.bump,
+    )]
+    pub holder_position: Option<Box<Account<'info, Position>>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub collateral_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CrankClaim<'info> {
+    pub fn crank_claim(&mut self, is_yes: bool) -> Result<Claimed> {
+        assert_not_locked!(self.market.locked);
+
+        require!(self.market.settled, MarketError::MarketNotSettled);
+        require!(
+            self.market.winner == Some(is_yes),
+            MarketError::LosingOutcome
+        );
+
+        let (holder_ata, supply, vault_tokens) = if is_yes {
+            (
+                self.holder_ata_yes.as_ref(),
+                self.mint_yes.supply,
+                self.vault_yes.amount,
+            )
+        } else {
+            (
+                self.holder_ata_no.as_ref(),
+                self.mint_no.supply,
+                self.vault_no.amount,
+            )
+        };
+        let holder_ata = holder_ata.ok_or(MarketError::MissingOutcomeAccount)?;
+
+        let holder_tokens = holder_ata.amount;
+        require!(holder_tokens > 0, MarketError::InsufficientBalance);
+
+        // The market PDA can only burn on the holder's behalf if the holder
+        // opted in with `approve_crank`.
+        require!(
+            holder_ata.delegate == COption::Some(self.market.key())
+                && holder_ata.delegated_amount >= holder_tokens,
+            MarketError::NotDelegated
+        );
+
+        let holder_payout =
+            claim_payout(holder_tokens, self.vault_usdc.amount, supply, vault_tokens)?;
+
+        let keeper_tip = (holder_payout as u128)
+            .checked_mul(self.market.keeper_tip_bps as u128)
+            .ok_or(MarketError::MathOverflow)?
+            .checked_div(BPS_DENOMINATOR as u128)
+            .ok_or(MarketError::MathOverflow)? as u64;
+
+        self.transfer_amount(
+            self.holder_ata_usdc.to_account_info(),
+            holder_payout
+                .checked_sub(keeper_tip)
+                .ok_or(MarketError::MathUnderflow)?,
+        )?;
+
+        if keeper_tip > 0 {
+            self.transfer_amount(self.keeper_ata_usdc.to_account_info(), keeper_tip)?;
+        }
+
+        self.burn_tokens(holder_ata.to_account_info(), holder_tokens, is_yes)?;
+
+        if let Some(position) = self.holder_position.as_mut() {
+            position.finalize(holder_payout)?;
+        }
+
+        Ok(Claimed {
+            market: self.market.key(),
+            user: self.holder.key(),
+            is_yes,
+            amount_burned: holder_tokens,
+            payout: holder_payout,
+            keeper_tip,
+        })
+    }
+
+    pub fn transfer_amount(&self, to: AccountInfo<'info>, amount: u64) -> Result<()> {
+        let accounts = TransferChecked {
+            from: self.vault_usdc.to_account_info(),
+            mint: self.mint_usdc.to_account_info(),
+            to,
+            authority: self.market.to_account_info(),
+        };
+
+        let seeds = &[
+            &b"market"[..],
+            &self.market.seed.to_le_bytes(),
+            &[self.market.market_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(
+            self.collateral_token_program.to_account_info(),
+            accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(ctx, amount, self.mint_usdc.decimals)
+    }
+
+    pub fn burn_tokens(&self, from: AccountInfo<'info>, amount: u64, is_yes: bool) -> Result<()> {
+        let mint = match is_yes {
+            true => self.mint_yes.to_account_info(),
+            false => self.mint_no.to_account_info(),
+        };
+
+        let cpi_accounts = Burn {
+            mint,
+            from,
+            authority: self.market.to_account_info(),
+        };
+
+        let seeds = &[
+            &b"market"[..],
+            &self.market.seed.to_le_bytes(),
+            &[self.market.market_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        burn(ctx, amount)
+    }
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ApproveCrank<'info> {
+    user: Signer<'info>,
+    #[account(
+        seeds = [b"market", market.seed.to_le_bytes().as_ref()],
+        bump = market.market_bump,
+    )]
+    pub market: Box<Account<'info, Market>>,
+    #[account(
+        mint::token_program = token_program,
+        mint::authority = market,
+    )]
+    mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    user_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> ApproveCrank<'info> {
+    /// Lets the market PDA burn up to `amount` of the user's outcome tokens
+    /// after settlement, so keepers can `crank_claim` on their behalf. An
+    /// `amount` of 0 withdraws the approval.
+    pub fn approve_crank(&self, amount: u64) -> Result<CrankApproved> {
+        let is_yes = if self.mint.key() == self.market.mint_yes {
+            true
+        } else if self.mint.key() == self.market.mint_no {
+            false
+        } else {
+            return err!(MarketError::InvalidToken);
+        };
+
+        let accounts = Approve {
+            to: self.user_ata.to_account_info(),
+            delegate: self.market.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(self.token_program.to_account_info(), accounts);
+
+        approve(ctx, amount)?;
+
+        Ok(CrankApproved {
+            market: self.market.key(),
+            user: self.user.key(),
+            is_yes,
+            amount,
+        })
+    }
+}
@@ -1,11 +1,20 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{mint_to, Mint, MintTo, TokenAccount, TokenInterface},
+    token_interface::{
+        mint_to, transfer_checked, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked,
+    },
 };
 
-use crate::{assert_non_zero, assert_not_expired, assert_not_locked, states::Market};
+use crate::{
+    assert_non_zero, assert_not_expired, assert_not_locked,
+    error::MarketError,
+    events::LiquidityAdded,
+    helpers::{is_native_mint, price_to_u64, wrap_sol, LMSRCalculator},
+    states::{AllowlistEntry, Market, Position},
+};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Deposit<'info> {
     #[account(mut)]
@@ -23,8 +32,7 @@ pub struct Deposit<'info> {
     )]
     mint_no: Box<InterfaceAccount<'info, Mint>>,
     #[account(
-        mut,
-        mint::token_program = token_program,
+        mint::token_program = collateral_token_program,
     )]
     mint_usdc: Box<InterfaceAccount<'info, Mint>>,
     #[account(
@@ -45,38 +53,163 @@ pub struct Deposit<'info> {
         mut,
         associated_token::mint = mint_usdc,
         associated_token::authority = market,
-        associated_token::token_program = token_program
+        associated_token::token_program = collateral_token_program
     )]
     vault_usdc: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Pays for the minted pairs. Wrapped SOL markets take it from the
+    /// user's lamports instead.
+    #[account(
+        mut,
+        associated_token::mint = mint_usdc,
+        associated_token::authority = user,
+        associated_token::token_program = collateral_token_program,
+    )]
+    user_ata_usdc: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     #[account(
         mut,
         has_one = mint_yes,
         has_one = mint_no,
+        has_one = mint_usdc,
         seeds = [b"market", market.seed.to_le_bytes().as_ref()],
         bump = market.market_bump
     )]
     market: Box<Account<'info, Market>>,
+    #[account(
+        has_one = market,
+        has_one = user,
+        seeds = [b"allowlist", market.key().as_ref(), user.key().as_ref()],
+        bump = allowlist_entry.bump,
+    )]
+    allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+    /// Opened with `open_position`; required while the market caps user
+    /// exposure.
+    #[account(
+        mut,
+        has_one = market,
+        has_one = user,
+        seeds = [b"position", market.key().as_ref(), user.key().as_ref()],
+        bump = position.bump,
+    )]
+    position: Option<Box<Account<'info, Position>>>,
 
     token_program: Interface<'info, TokenInterface>,
+    collateral_token_program: Interface<'info, TokenInterface>,
     associated_token_program: Program<'info, AssociatedToken>,
     system_program: Program<'info, System>,
 }
 
 impl<'info> Deposit<'info> {
-    pub fn deposit(&mut self, min_no: u64, min_yes: u64, expiration: i64) -> Result<()> {
+    pub fn deposit(
+        &mut self,
+        min_no: u64,
+        min_yes: u64,
+        expiration: i64,
+        proof: &[[u8; 32]],
+    ) -> Result<LiquidityAdded> {
         assert_not_locked!(self.market.locked);
         assert_not_expired!(expiration);
         assert_non_zero!([min_yes, min_no]);
+        self.market
+            .check_trading_open(Clock::get()?.unix_timestamp)?;
+        // Minting one side more than the other would move the price for free.
+        require!(min_yes == min_no, MarketError::UnbalancedDeposit);
+        let pairs = min_yes;
+
+        self.market
+            .check_access(&self.user.key(), self.allowlist_entry.is_some(), proof)?;
+
+        self.market.limits.check_trade_size(pairs)?;
+
+        // Balanced deposits leave the price where it was, but they answer to
+        // the circuit breaker like every other way into the vaults.
+        let (vault_yes, vault_no) = (self.vault_yes.amount, self.vault_no.amount);
+        let price_before = self.price_yes(vault_yes, vault_no)?;
+        let price_after = self.price_yes(
+            vault_yes.checked_add(pairs).ok_or(MarketError::MathOverflow)?,
+            vault_no.checked_add(pairs).ok_or(MarketError::MathOverflow)?,
+        )?;
+        self.market.check_circuit_breaker(price_before, price_after)?;
 
-        self.mint_token(min_yes, true)?;
-        self.mint_token(min_no, false)?;
+        self.collect_collateral(pairs)?;
+        self.mint_token(pairs, true)?;
+        self.mint_token(pairs, false)?;
+        self.market.trading_opened = true;
 
+        self.record_deposit(pairs)?;
         self.market
+            .limits
+            .check_open_interest(self.open_interest()?)?;
+
+        self.market.total_liquidity = self
+            .market
             .total_liquidity
-            .checked_add(min_yes.checked_add(min_no).unwrap())
-            .unwrap();
+            .checked_add(pairs)
+            .ok_or(MarketError::MathOverflow)?;
+
+        Ok(LiquidityAdded {
+            market: self.market.key(),
+            user: self.user.key(),
+            amount_yes: min_yes,
+            amount_no: min_no,
+        })
+    }
+
+    /// Books the collateral paid for a deposit on the user's position and
+    /// checks their exposure. Capped markets need a position.
+    fn record_deposit(&mut self, collateral: u64) -> Result<()> {
+        let position = match self.position.as_mut() {
+            Some(position) => position,
+            None if self.market.limits.max_user_exposure == 0 => return Ok(()),
+            None => return err!(MarketError::PositionRequired),
+        };
+
+        position.record_deposit(collateral)?;
+        self.market
+            .limits
+            .check_user_exposure(position.net_exposure())
+    }
+
+    fn price_yes(&self, vault_yes: u64, vault_no: u64) -> Result<u64> {
+        let calculator = LMSRCalculator::new(self.market.liquidity()?, vault_yes, vault_no);
+        price_to_u64(calculator.calculate_price(true)?)
+    }
+
+    /// Takes one unit of collateral per minted YES+NO pair into `vault_usdc`,
+    /// which is what the pair redeems for.
+    fn collect_collateral(&self, amount: u64) -> Result<()> {
+        if is_native_mint(&self.mint_usdc.key()) {
+            return wrap_sol(
+                self.user.to_account_info(),
+                self.vault_usdc.to_account_info(),
+                self.system_program.to_account_info(),
+                self.collateral_token_program.to_account_info(),
+                amount,
+            );
+        }
+
+        let from = self
+            .user_ata_usdc
+            .as_ref()
+            .ok_or(MarketError::MissingCollateralAccount)?;
+
+        let accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: self.mint_usdc.to_account_info(),
+            to: self.vault_usdc.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(self.collateral_token_program.to_account_info(), accounts);
+
+        transfer_checked(ctx, amount, self.mint_usdc.decimals)
+    }
 
-        Ok(())
+    /// Outcome tokens held outside the vaults. Deposits mint into the vaults,
+    /// so this can't grow here, but the cap is checked on every entry point.
+    pub fn open_interest(&self) -> Result<u64> {
+        let yes = self.mint_yes.supply.saturating_sub(self.vault_yes.amount);
+        let no = self.mint_no.supply.saturating_sub(self.vault_no.amount);
+        Ok(yes.checked_add(no).ok_or(MarketError::MathOverflow)?)
     }
 
     pub fn mint_token(&mut self, amount: u64, is_yes: bool) -> Result<()> {
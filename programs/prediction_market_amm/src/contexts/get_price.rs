@@ -0,0 +1,83 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    helpers::{price_to_u64, LMSRCalculator},
+    oracle::{MarketStatus, PriceData},
+    states::{Market, PriceHistory},
+};
+
+#[derive(Accounts)]
+pub struct GetPrice<'info> {
+    #[account(
+        mint::token_program = token_program,
+        mint::authority = market
+    )]
+    mint_yes: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = token_program,
+        mint::authority = market
+    )]
+    mint_no: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        associated_token::mint = mint_yes,
+        associated_token::authority = market,
+        associated_token::token_program = token_program,
+    )]
+    vault_yes: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        associated_token::mint = mint_no,
+        associated_token::authority = market,
+        associated_token::token_program = token_program,
+    )]
+    vault_no: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        has_one = mint_yes,
+        has_one = mint_no,
+        seeds = [b"market", market.seed.to_le_bytes().as_ref()],
+        bump = market.market_bump,
+    )]
+    pub market: Box<Account<'info, Market>>,
+    #[account(
+        has_one = market,
+        seeds = [b"price_history", market.key().as_ref()],
+        bump = price_history.bump,
+    )]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+
+    /// The outcome mints' program, so Token-2022 markets can be read too.
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> GetPrice<'info> {
+    /// Spot price and status always come back; the TWAP only when the
+    /// history covers `twap_window`, so callers can fall back to the spot.
+    pub fn get_price(&self, twap_window: i64) -> Result<PriceData> {
+        let now = Clock::get()?.unix_timestamp;
+
+        let calculator = LMSRCalculator::new(
+            self.market.liquidity()?,
+            self.vault_yes.amount,
+            self.vault_no.amount,
+        );
+
+        let status = if self.market.settled {
+            MarketStatus::Settled
+        } else if self.market.locked {
+            MarketStatus::Locked
+        } else if now > self.market.end_time {
+            MarketStatus::Expired
+        } else {
+            MarketStatus::Open
+        };
+
+        Ok(PriceData {
+            market: self.market.key(),
+            spot_price: price_to_u64(calculator.calculate_price(true)?)?,
+            twap: self.price_history.twap(twap_window, now).ok(),
+            status,
+            winner: self.market.winner,
+            last_update_slot: self.price_history.latest()?.slot,
+        })
+    }
+}
@@ -1,56 +1,81 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{
+    prelude::*,
+    system_program::{create_account, CreateAccount},
+};
+#[cfg(feature = "metaplex")]
+use anchor_spl::metadata::{
+    create_metadata_accounts_v3, mpl_token_metadata::types::DataV2, CreateMetadataAccountsV3,
+    Metadata,
+};
 use anchor_spl::{
-    associated_token::{create_idempotent, AssociatedToken},
-    metadata::{
-        create_metadata_accounts_v3, mpl_token_metadata::types::DataV2, CreateMetadataAccountsV3,
-        Metadata,
+    associated_token::{
+        create_idempotent, get_associated_token_address_with_program_id, AssociatedToken,
+    },
+    token_2022,
+    token_interface::{
+        initialize_mint2, metadata_pointer_initialize, mint_to,
+        spl_token_2022::{extension::ExtensionType, state::Mint as SplMint},
+        spl_token_metadata_interface::state::TokenMetadata,
+        token_metadata_initialize, transfer_checked, InitializeMint2, MetadataPointerInitialize,
+        Mint, MintTo, TokenAccount, TokenInterface, TokenMetadataInitialize, TransferChecked,
     },
-    token_interface::{Mint, TokenInterface},
 };
+use rust_decimal::prelude::*;
 
-use crate::states::Market;
+#[cfg(feature = "metaplex")]
+use crate::helpers::metadata_address;
+use crate::{
+    error::MarketError,
+    events::MarketCreated,
+    helpers::{
+        initial_shares, is_native_mint, liquidity_for_decimals, wrap_sol, LMSRCalculator,
+        MAX_FEE_BPS, MAX_KEEPER_TIP_BPS, MAX_MARKET_NAME_LEN, MAX_TOKEN_NAME_LEN,
+        MAX_TOKEN_SYMBOL_LEN, MAX_TOKEN_URI_LEN,
+    },
+    states::{CircuitBreaker, Market, MarketLimits, OutcomeMetadata, PriceHistory},
+};
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(seed: u64)]
 pub struct Initialize<'info> {
     #[account(mut)]
     signer: Signer<'info>,
-    #[account(
-        init,
-        seeds = [b"yes_mint", seed.to_le_bytes().as_ref()],
-        bump,
-        payer = signer,
-        mint::token_program = token_program,
-        mint::authority = market,
-        mint::decimals = 6
-    )]
-    mint_yes: Box<InterfaceAccount<'info, Mint>>,
-    #[account(
-        init,
-        seeds = [b"no_mint", seed.to_le_bytes().as_ref()],
-        bump,
-        payer = signer,
-        mint::token_program = token_program,
-        mint::authority = market,
-        mint::decimals = 6
-    )]
-    mint_no: Box<InterfaceAccount<'info, Mint>>,
+    /// CHECK: Created in `create_outcome_mint`
+    #[account(mut, seeds = [b"yes_mint", seed.to_le_bytes().as_ref()], bump)]
+    mint_yes: UncheckedAccount<'info>,
+    /// CHECK: Created in `create_outcome_mint`
+    #[account(mut, seeds = [b"no_mint", seed.to_le_bytes().as_ref()], bump)]
+    mint_no: UncheckedAccount<'info>,
+    #[account(mint::token_program = collateral_token_program)]
     mint_usdc: Box<InterfaceAccount<'info, Mint>>,
-    /// CHECK: This account is not read or written in this instruction
+    treasury: SystemAccount<'info>,
+    /// CHECK: Created here; address checked in `validate_addresses`
     #[account(mut)]
     vault_yes: UncheckedAccount<'info>,
-    /// CHECK: This account is not read or written in this instruction
+    /// CHECK: Created here; address checked in `validate_addresses`
     #[account(mut)]
     vault_no: UncheckedAccount<'info>,
-    /// CHECK: This account is not read or written in this instruction
+    /// CHECK: Created here; address checked in `validate_addresses`
     #[account(mut)]
     vault_usdc: UncheckedAccount<'info>,
-    /// CHECK: New Metaplex Account being created
+    /// Pays the subsidy. Wrapped SOL markets take it from the signer's
+    /// lamports instead.
+    #[account(
+        mut,
+        associated_token::mint = mint_usdc,
+        associated_token::authority = signer,
+        associated_token::token_program = collateral_token_program,
+    )]
+    signer_ata_usdc: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// CHECK: New Metaplex account, only for `OutcomeMetadata::Metaplex`;
+    /// address checked in `validate_metaplex_accounts`
     #[account(mut)]
-    metadata_yes: UncheckedAccount<'info>,
-    /// CHECK: New Metaplex Account being created
+    metadata_yes: Option<UncheckedAccount<'info>>,
+    /// CHECK: New Metaplex account, only for `OutcomeMetadata::Metaplex`;
+    /// address checked in `validate_metaplex_accounts`
     #[account(mut)]
-    metadata_no: UncheckedAccount<'info>,
+    metadata_no: Option<UncheckedAccount<'info>>,
     #[account(
         init,
         payer = signer,
@@ -59,15 +84,28 @@ pub struct Initialize<'info> {
         space = 8 + Market::INIT_SPACE
     )]
     market: Box<Account<'info, Market>>,
+    #[account(
+        init,
+        payer = signer,
+        seeds = [b"price_history", market.key().as_ref()],
+        bump,
+        space = 8 + PriceHistory::INIT_SPACE
+    )]
+    price_history: Box<Account<'info, PriceHistory>>,
 
     system_program: Program<'info, System>,
     token_program: Interface<'info, TokenInterface>,
-    token_metadata_program: Program<'info, Metadata>,
+    /// Owns the collateral mint; may differ from the outcome mints' program.
+    collateral_token_program: Interface<'info, TokenInterface>,
+    /// CHECK: Metaplex program, only for `OutcomeMetadata::Metaplex`;
+    /// checked in `validate_metaplex_accounts`
+    token_metadata_program: Option<UncheckedAccount<'info>>,
     associated_token_program: Program<'info, AssociatedToken>,
     rent: Sysvar<'info, Rent>,
 }
 
 impl<'info> Initialize<'info> {
+    #[allow(clippy::too_many_arguments)]
     pub fn save_market(
         &mut self,
         seed: u64,
@@ -80,21 +118,109 @@ impl<'info> Initialize<'info> {
         token_no_uri: String,
         fee: u16,
         end_time: i64,
+        keeper_tip_bps: u16,
+        limits: MarketLimits,
+        circuit_breaker: CircuitBreaker,
+        initial_price: u64,
+        outcome_metadata: OutcomeMetadata,
+        observation_interval: u32,
+        claim_window: u32,
         bumps: &InitializeBumps,
-    ) -> Result<()> {
+    ) -> Result<MarketCreated> {
+        require!(
+            keeper_tip_bps <= MAX_KEEPER_TIP_BPS,
+            MarketError::KeeperTipTooHigh
+        );
+
+        let clock = Clock::get()?;
+        require!(end_time > clock.unix_timestamp, MarketError::InvalidEndTime);
+        require!(fee <= MAX_FEE_BPS, MarketError::InvalidFee);
+        require!(
+            observation_interval > 0,
+            MarketError::InvalidObservationInterval
+        );
+        require!(
+            name.len() <= MAX_MARKET_NAME_LEN,
+            MarketError::MarketNameTooLong
+        );
+        for (token_name, symbol, uri) in [
+            (&token_yes_name, &token_yes_symbol, &token_yes_uri),
+            (&token_no_name, &token_no_symbol, &token_no_uri),
+        ] {
+            require!(
+                token_name.len() <= MAX_TOKEN_NAME_LEN,
+                MarketError::TokenNameTooLong
+            );
+            require!(
+                symbol.len() <= MAX_TOKEN_SYMBOL_LEN,
+                MarketError::TokenSymbolTooLong
+            );
+            require!(uri.len() <= MAX_TOKEN_URI_LEN, MarketError::TokenUriTooLong);
+        }
+        self.validate_addresses(outcome_metadata)?;
+
+        let collateral_decimals = self.mint_usdc.decimals;
+        let b = liquidity_for_decimals(collateral_decimals)?;
+        let (initial_yes, initial_no) = initial_shares(b, initial_price)?;
+        let subsidy = LMSRCalculator::new(b, initial_yes, initial_no)
+            .max_loss()?
+            .ceil()
+            .to_u64()
+            .ok_or(MarketError::MathOverflow)?;
+
         self.market.set_inner(Market {
             market_name: name,
             seed,
             mint_yes: self.mint_yes.key(),
             mint_no: self.mint_no.key(),
+            mint_usdc: self.mint_usdc.key(),
             total_liquidity: 0,
             end_time,
             fee,
             locked: false,
             settled: false,
+            keeper_tip_bps,
+            payer: self.signer.key(),
+            treasury: self.treasury.key(),
+            settled_at: 0,
+            claim_window,
+            limits,
+            authority: self.signer.key(),
+            access_root: None,
+            circuit_breaker,
+            reference_price: initial_price,
+            reference_slot: clock.slot,
+            reference_timestamp: clock.unix_timestamp,
+            initial_price,
+            subsidy,
+            outcome_metadata,
+            winner: None,
+            collateral_decimals,
+            trading_opened: false,
             market_bump: bumps.market,
         });
 
+        self.price_history.market = self.market.key();
+        self.price_history.bump = bumps.price_history;
+        self.price_history.observation_interval = observation_interval;
+        self.price_history
+            .record(clock.unix_timestamp, clock.slot, initial_price)?;
+
+        self.create_outcome_mint(
+            true,
+            token_yes_name,
+            token_yes_symbol,
+            token_yes_uri,
+            bumps.mint_yes,
+        )?;
+        self.create_outcome_mint(
+            false,
+            token_no_name,
+            token_no_symbol,
+            token_no_uri,
+            bumps.mint_no,
+        )?;
+
         create_idempotent(CpiContext::new(
             self.associated_token_program.to_account_info(),
             anchor_spl::associated_token::Create {
@@ -103,7 +229,7 @@ impl<'info> Initialize<'info> {
                 authority: self.market.to_account_info(),
                 mint: self.mint_usdc.to_account_info(),
                 system_program: self.system_program.to_account_info(),
-                token_program: self.token_program.to_account_info(),
+                token_program: self.collateral_token_program.to_account_info(),
             },
         ))?;
         create_idempotent(CpiContext::new(
@@ -129,20 +255,263 @@ impl<'info> Initialize<'info> {
             },
         ))?;
 
-        let token_yes_data = DataV2 {
-            name: token_yes_name,
-            symbol: token_yes_symbol,
-            uri: token_yes_uri,
-            seller_fee_basis_points: 0,
-            creators: None,
-            collection: None,
-            uses: None,
+        self.fund_subsidy(subsidy)?;
+
+        // Seed both vaults, offset so the market opens at `initial_price`.
+        self.mint_to_vault(true, initial_yes)?;
+        self.mint_to_vault(false, initial_no)?;
+
+        Ok(MarketCreated {
+            market: self.market.key(),
+            seed,
+            market_name: self.market.market_name.clone(),
+            mint_yes: self.mint_yes.key(),
+            mint_no: self.mint_no.key(),
+            mint_usdc: self.mint_usdc.key(),
+            fee,
+            end_time,
+            keeper_tip_bps,
+            initial_price,
+            subsidy,
+            collateral_decimals,
+        })
+    }
+
+    /// Moves the market maker's worst-case loss from the creator into
+    /// `vault_usdc`, so winners are paid in full whatever the outcome.
+    fn fund_subsidy(&self, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        if is_native_mint(&self.mint_usdc.key()) {
+            return wrap_sol(
+                self.signer.to_account_info(),
+                self.vault_usdc.to_account_info(),
+                self.system_program.to_account_info(),
+                self.collateral_token_program.to_account_info(),
+                amount,
+            );
+        }
+
+        let from = self
+            .signer_ata_usdc
+            .as_ref()
+            .ok_or(MarketError::MissingCollateralAccount)?;
+
+        let accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: self.mint_usdc.to_account_info(),
+            to: self.vault_usdc.to_account_info(),
+            authority: self.signer.to_account_info(),
         };
 
-        let token_no_data = DataV2 {
-            name: token_no_name,
-            symbol: token_no_symbol,
-            uri: token_no_uri,
+        let ctx = CpiContext::new(self.collateral_token_program.to_account_info(), accounts);
+
+        transfer_checked(ctx, amount, self.mint_usdc.decimals)
+    }
+
+    /// The vaults and metadata accounts are passed unchecked because they are
+    /// created here, so their addresses are verified before anything else.
+    fn validate_addresses(&self, outcome_metadata: OutcomeMetadata) -> Result<()> {
+        let market = self.market.key();
+        let token_program = self.token_program.key();
+        let collateral_token_program = self.collateral_token_program.key();
+
+        for (vault, mint, token_program) in [
+            (self.vault_yes.key(), self.mint_yes.key(), token_program),
+            (self.vault_no.key(), self.mint_no.key(), token_program),
+            (
+                self.vault_usdc.key(),
+                self.mint_usdc.key(),
+                collateral_token_program,
+            ),
+        ] {
+            require_keys_eq!(
+                vault,
+                get_associated_token_address_with_program_id(&market, &mint, &token_program),
+                MarketError::InvalidVaultAddress
+            );
+        }
+
+        match outcome_metadata {
+            OutcomeMetadata::Metaplex => self.validate_metaplex_accounts(),
+            OutcomeMetadata::TokenExtensions => {
+                require_keys_eq!(
+                    token_program,
+                    token_2022::ID,
+                    MarketError::Token2022Required
+                );
+                Ok(())
+            }
+        }
+    }
+
+    #[cfg(feature = "metaplex")]
+    fn validate_metaplex_accounts(&self) -> Result<()> {
+        let Some(metadata_program) = &self.token_metadata_program else {
+            return err!(MarketError::MissingMetadataAccounts);
+        };
+        require_keys_eq!(
+            metadata_program.key(),
+            Metadata::id(),
+            ErrorCode::InvalidProgramId
+        );
+
+        for (metadata, mint) in [
+            (&self.metadata_yes, &self.mint_yes),
+            (&self.metadata_no, &self.mint_no),
+        ] {
+            let Some(metadata) = metadata else {
+                return err!(MarketError::MissingMetadataAccounts);
+            };
+            require_keys_eq!(
+                metadata.key(),
+                metadata_address(&mint.key()),
+                MarketError::InvalidMetadataAddress
+            );
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "metaplex"))]
+    fn validate_metaplex_accounts(&self) -> Result<()> {
+        err!(MarketError::MetaplexUnavailable)
+    }
+
+    /// Creates an outcome mint at its PDA. Its metadata lives on the mint
+    /// itself for Token-2022 extensions, or in a Metaplex account.
+    fn create_outcome_mint(
+        &self,
+        is_yes: bool,
+        name: String,
+        symbol: String,
+        uri: String,
+        bump: u8,
+    ) -> Result<()> {
+        let outcome_metadata = self.market.outcome_metadata;
+        let (mint, prefix) = match is_yes {
+            true => (self.mint_yes.to_account_info(), &b"yes_mint"[..]),
+            false => (self.mint_no.to_account_info(), &b"no_mint"[..]),
+        };
+
+        let (extensions, metadata_space) = match outcome_metadata {
+            OutcomeMetadata::Metaplex => (vec![], 0),
+            OutcomeMetadata::TokenExtensions => {
+                let metadata = TokenMetadata {
+                    name: name.clone(),
+                    symbol: symbol.clone(),
+                    uri: uri.clone(),
+                    ..Default::default()
+                };
+                (
+                    vec![ExtensionType::MetadataPointer],
+                    metadata.tlv_size_of()?,
+                )
+            }
+        };
+        let space = ExtensionType::try_calculate_account_len::<SplMint>(&extensions)?;
+
+        // `token_metadata_initialize` reallocs the mint to append the
+        // metadata, so its rent is paid up front.
+        let mint_seeds = &[prefix, &self.market.seed.to_le_bytes(), &[bump]];
+        create_account(
+            CpiContext::new_with_signer(
+                self.system_program.to_account_info(),
+                CreateAccount {
+                    from: self.signer.to_account_info(),
+                    to: mint.clone(),
+                },
+                &[&mint_seeds[..]],
+            ),
+            Rent::get()?.minimum_balance(space + metadata_space),
+            space as u64,
+            &self.token_program.key(),
+        )?;
+
+        let market = self.market.key();
+        if outcome_metadata == OutcomeMetadata::TokenExtensions {
+            metadata_pointer_initialize(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    MetadataPointerInitialize {
+                        token_program_id: self.token_program.to_account_info(),
+                        mint: mint.clone(),
+                    },
+                ),
+                Some(market),
+                Some(mint.key()),
+            )?;
+        }
+
+        // Outcome shares use the collateral's base unit. The market is also
+        // freeze authority so losing-side transfers can be halted once the
+        // market settles.
+        initialize_mint2(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                InitializeMint2 { mint: mint.clone() },
+            ),
+            self.market.collateral_decimals,
+            &market,
+            Some(&market),
+        )?;
+
+        match outcome_metadata {
+            OutcomeMetadata::Metaplex => {
+                self.create_metaplex_metadata(is_yes, mint, name, symbol, uri)
+            }
+            OutcomeMetadata::TokenExtensions => {
+                let seeds = &[
+                    &b"market"[..],
+                    &self.market.seed.to_le_bytes(),
+                    &[self.market.market_bump],
+                ];
+                let signer_seeds = &[&seeds[..]];
+
+                token_metadata_initialize(
+                    CpiContext::new_with_signer(
+                        self.token_program.to_account_info(),
+                        TokenMetadataInitialize {
+                            token_program_id: self.token_program.to_account_info(),
+                            metadata: mint.clone(),
+                            update_authority: self.market.to_account_info(),
+                            mint_authority: self.market.to_account_info(),
+                            mint,
+                        },
+                        signer_seeds,
+                    ),
+                    name,
+                    symbol,
+                    uri,
+                )
+            }
+        }
+    }
+
+    #[cfg(feature = "metaplex")]
+    fn create_metaplex_metadata(
+        &self,
+        is_yes: bool,
+        mint: AccountInfo<'info>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        let metadata = match is_yes {
+            true => &self.metadata_yes,
+            false => &self.metadata_no,
+        };
+        let (Some(metadata_program), Some(metadata)) = (&self.token_metadata_program, metadata)
+        else {
+            return err!(MarketError::MissingMetadataAccounts);
+        };
+
+        let data = DataV2 {
+            name,
+            symbol,
+            uri,
             seller_fee_basis_points: 0,
             creators: None,
             collection: None,
@@ -156,13 +525,13 @@ impl<'info> Initialize<'info> {
         ];
         let signer_seeds = &[&seeds[..]];
 
-        let metadata_yes_ctx = CpiContext::new_with_signer(
-            self.token_metadata_program.to_account_info(),
+        let ctx = CpiContext::new_with_signer(
+            metadata_program.to_account_info(),
             CreateMetadataAccountsV3 {
                 payer: self.signer.to_account_info(),
                 update_authority: self.market.to_account_info(),
-                mint: self.mint_yes.to_account_info(),
-                metadata: self.metadata_yes.to_account_info(),
+                mint,
+                metadata: metadata.to_account_info(),
                 mint_authority: self.market.to_account_info(),
                 system_program: self.system_program.to_account_info(),
                 rent: self.rent.to_account_info(),
@@ -170,7 +539,36 @@ impl<'info> Initialize<'info> {
             signer_seeds,
         );
 
-        create_metadata_accounts_v3(metadata_yes_ctx, token_yes_data, false, true, None)?;
+        create_metadata_accounts_v3(ctx, data, false, true, None)
+    }
+
+    #[cfg(not(feature = "metaplex"))]
+    fn create_metaplex_metadata(
+        &self,
+        _is_yes: bool,
+        _mint: AccountInfo<'info>,
+        _name: String,
+        _symbol: String,
+        _uri: String,
+    ) -> Result<()> {
+        err!(MarketError::MetaplexUnavailable)
+    }
+
+    fn mint_to_vault(&self, is_yes: bool, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let (mint, to) = match is_yes {
+            true => (
+                self.mint_yes.to_account_info(),
+                self.vault_yes.to_account_info(),
+            ),
+            false => (
+                self.mint_no.to_account_info(),
+                self.vault_no.to_account_info(),
+            ),
+        };
 
         let seeds = &[
             &b"market"[..],
@@ -179,22 +577,16 @@ impl<'info> Initialize<'info> {
         ];
         let signer_seeds = &[&seeds[..]];
 
-        let metadata_no_ctx = CpiContext::new_with_signer(
-            self.token_metadata_program.to_account_info(),
-            CreateMetadataAccountsV3 {
-                payer: self.signer.to_account_info(),
-                update_authority: self.market.to_account_info(),
-                mint: self.mint_no.to_account_info(),
-                metadata: self.metadata_no.to_account_info(),
-                mint_authority: self.market.to_account_info(),
-                system_program: self.system_program.to_account_info(),
-                rent: self.rent.to_account_info(),
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            MintTo {
+                mint,
+                to,
+                authority: self.market.to_account_info(),
             },
             signer_seeds,
         );
 
-        create_metadata_accounts_v3(metadata_no_ctx, token_no_data, false, true, None)?;
-
-        Ok(())
+        mint_to(ctx, amount)
     }
 }
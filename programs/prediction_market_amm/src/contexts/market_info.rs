@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::MarketError,
+    events::MarketInfoUpdated,
+    states::{Market, MarketInfo, MarketInfoArgs},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetMarketInfo<'info> {
+    #[account(mut)]
+    authority: Signer<'info>,
+    #[account(
+        has_one = authority,
+        seeds = [b"market", market.seed.to_le_bytes().as_ref()],
+        bump = market.market_bump,
+    )]
+    market: Box<Account<'info, Market>>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + MarketInfo::INIT_SPACE,
+        seeds = [b"market_info", market.key().as_ref()],
+        bump,
+    )]
+    market_info: Box<Account<'info, MarketInfo>>,
+
+    system_program: Program<'info, System>,
+}
+
+impl<'info> SetMarketInfo<'info> {
+    /// Creates or replaces the market's info until the first swap or deposit
+    /// opens trading; the info is fixed from then on.
+    pub fn set_market_info(
+        &mut self,
+        info: MarketInfoArgs,
+        bumps: &SetMarketInfoBumps,
+    ) -> Result<MarketInfoUpdated> {
+        info.validate()?;
+
+        require!(!self.market.trading_opened, MarketError::TradingAlreadyOpen);
+
+        self.market_info.set_inner(MarketInfo {
+            market: self.market.key(),
+            title: info.title,
+            category: info.category,
+            tags: info.tags,
+            resolution_source: info.resolution_source,
+            rules_hash: info.rules_hash,
+            event_id: info.event_id,
+            bump: bumps.market_info,
+        });
+
+        Ok(MarketInfoUpdated {
+            market: self.market.key(),
+            market_info: self.market_info.key(),
+            title: self.market_info.title.clone(),
+            category: self.market_info.category,
+            rules_hash: self.market_info.rules_hash,
+        })
+    }
+}
@@ -15,3 +15,27 @@ pub use claim::*;
 
 pub mod update;
 pub use update::*;
+
+pub mod claim_many;
+pub use claim_many::*;
+
+pub mod crank_claim;
+pub use crank_claim::*;
+
+pub mod close_market;
+pub use close_market::*;
+
+pub mod get_price;
+pub use get_price::*;
+
+pub mod quote_swap;
+pub use quote_swap::*;
+
+pub mod access;
+pub use access::*;
+
+pub mod market_info;
+pub use market_info::*;
+
+pub mod position;
+pub use position::*;
@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::MarketError,
+    states::{Market, Position},
+};
+
+#[derive(Accounts)]
+pub struct OpenPosition<'info> {
+    #[account(mut)]
+    user: Signer<'info>,
+    #[account(
+        seeds = [b"market", market.seed.to_le_bytes().as_ref()],
+        bump = market.market_bump,
+    )]
+    market: Box<Account<'info, Market>>,
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [b"position", market.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    position: Box<Account<'info, Position>>,
+
+    system_program: Program<'info, System>,
+}
+
+impl<'info> OpenPosition<'info> {
+    pub fn open_position(&mut self, bumps: &OpenPositionBumps) -> Result<()> {
+        self.position.set_inner(Position {
+            market: self.market.key(),
+            user: self.user.key(),
+            yes_bought: 0,
+            yes_sold: 0,
+            no_bought: 0,
+            no_sold: 0,
+            usdc_spent: 0,
+            usdc_received: 0,
+            fees_paid: 0,
+            liquidity_added: 0,
+            payout: 0,
+            realized_pnl: 0,
+            finalized: false,
+            bump: bumps.position,
+        });
+        Ok(())
+    }
+}
+
+/// Returns a position's rent to its user once it can no longer change: after
+/// a claim finalized it, or once its market is closed.
+#[derive(Accounts)]
+pub struct ClosePositionAccount<'info> {
+    #[account(mut)]
+    user: Signer<'info>,
+    /// CHECK: Matched by `has_one` on the position; may already be closed.
+    market: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        close = user,
+        has_one = market,
+        has_one = user,
+        seeds = [b"position", market.key().as_ref(), user.key().as_ref()],
+        bump = position.bump,
+    )]
+    position: Box<Account<'info, Position>>,
+}
+
+impl<'info> ClosePositionAccount<'info> {
+    pub fn close_position_account(&self) -> Result<()> {
+        require!(
+            self.position.finalized || self.market.data_is_empty(),
+            MarketError::PositionOpen
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    assert_non_zero,
+    helpers::{quote_lmsr_swap, SwapQuote},
+    states::Market,
+};
+
+#[derive(Accounts)]
+pub struct QuoteSwap<'info> {
+    #[account(
+        mint::token_program = token_program,
+        mint::authority = market
+    )]
+    mint_yes: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = token_program,
+        mint::authority = market
+    )]
+    mint_no: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        associated_token::mint = mint_yes,
+        associated_token::authority = market,
+        associated_token::token_program = token_program,
+    )]
+    vault_yes: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        associated_token::mint = mint_no,
+        associated_token::authority = market,
+        associated_token::token_program = token_program,
+    )]
+    vault_no: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        has_one = mint_yes,
+        has_one = mint_no,
+        seeds = [b"market", market.seed.to_le_bytes().as_ref()],
+        bump = market.market_bump,
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    /// The outcome mints' program, so Token-2022 markets can be quoted too.
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> QuoteSwap<'info> {
+    pub fn quote_swap(&self, is_buying: bool, amount_in: u64, is_yes: bool) -> Result<SwapQuote> {
+        assert_non_zero!([amount_in]);
+
+        quote_lmsr_swap(
+            self.market.liquidity()?,
+            self.market.fee,
+            amount_in,
+            self.vault_yes.amount,
+            self.vault_no.amount,
+            is_buying,
+            is_yes,
+        )
+    }
+}
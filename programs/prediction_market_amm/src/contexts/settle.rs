@@ -1,32 +1,294 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+#[cfg(feature = "metaplex")]
+use anchor_spl::metadata::{
+    mpl_token_metadata::{accounts::Metadata as MetadataState, types::DataV2},
+    update_metadata_accounts_v2, Metadata, UpdateMetadataAccountsV2,
+};
+use anchor_spl::token_interface::{
+    freeze_account,
+    spl_token_2022::{
+        extension::{BaseStateWithExtensions, StateWithExtensions},
+        state::Mint as SplMint,
+    },
+    spl_token_metadata_interface::state::{Field, TokenMetadata},
+    token_metadata_update_field, FreezeAccount, Mint, TokenAccount, TokenInterface,
+    TokenMetadataUpdateField,
+};
 
-use crate::{assert_not_locked, error::MarketError, states::Market};
+#[cfg(feature = "metaplex")]
+use crate::helpers::metadata_address;
+use crate::{
+    assert_not_locked,
+    error::MarketError,
+    events::{LosingAccountFrozen, MarketSettled},
+    helpers::settled_token_name,
+    states::{Market, OutcomeMetadata},
+};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct SettleMarket<'info> {
     #[account(mut)]
-    admin: Signer<'info>,
+    authority: Signer<'info>,
     #[account(
         mut,
+        has_one = authority,
+        has_one = mint_yes,
+        has_one = mint_no,
         seeds = [b"market", market.seed.to_le_bytes().as_ref()],
         bump = market.market_bump,
     )]
     pub market: Account<'info, Market>,
+    /// CHECK: Matched by `has_one`; its metadata is renamed with `mark_outcomes`
+    #[account(mut)]
+    mint_yes: UncheckedAccount<'info>,
+    /// CHECK: Matched by `has_one`; its metadata is renamed with `mark_outcomes`
+    #[account(mut)]
+    mint_no: UncheckedAccount<'info>,
+    /// CHECK: Metaplex account, only for `OutcomeMetadata::Metaplex`;
+    /// address checked in `rename_metaplex`
+    #[account(mut)]
+    metadata_yes: Option<UncheckedAccount<'info>>,
+    /// CHECK: Metaplex account, only for `OutcomeMetadata::Metaplex`;
+    /// address checked in `rename_metaplex`
+    #[account(mut)]
+    metadata_no: Option<UncheckedAccount<'info>>,
+    /// CHECK: Metaplex program, only for `OutcomeMetadata::Metaplex`;
+    /// checked in `rename_metaplex`
+    token_metadata_program: Option<UncheckedAccount<'info>>,
+    token_program: Interface<'info, TokenInterface>,
+    system_program: Program<'info, System>,
 }
 
 impl<'info> SettleMarket<'info> {
-    pub fn settle(&mut self, is_resolved: bool) -> Result<()> {
+    /// Resolving needs the winner. `mark_outcomes` also renames both outcome
+    /// tokens to WON/LOST, which needs the metadata accounts.
+    pub fn settle(
+        &mut self,
+        is_resolved: bool,
+        winner: Option<bool>,
+        mark_outcomes: bool,
+    ) -> Result<MarketSettled> {
         assert_not_locked!(self.market.locked);
 
-        require!(!self.market.settled, MarketError::MarketAlreadySettled);
+        require!(!self.market.settled, MarketError::MarketSettled);
 
         require!(Clock::get()?.unix_timestamp > self.market.end_time, MarketError::MarketNotEnded);
 
         if is_resolved {
+            // Claims only pay the winning side, so a resolution must name it.
+            let yes_won = winner.ok_or(MarketError::WinnerRequired)?;
+
             self.market.settled = true;
+            self.market.settled_at = Clock::get()?.unix_timestamp;
+            self.market.winner = Some(yes_won);
+
+            if mark_outcomes {
+                self.mark_outcome(true, yes_won)?;
+                self.mark_outcome(false, !yes_won)?;
+            }
         } else {
             self.market.settled = false;
         }
-        Ok(())
+        Ok(MarketSettled {
+            market: self.market.key(),
+            settled: self.market.settled,
+            settled_at: self.market.settled_at,
+            winner: self.market.winner,
+        })
     }
-}
\ No newline at end of file
+
+    /// Appends "WON" or "LOST" to an outcome token's name so it stops
+    /// passing for a live position wherever it is listed.
+    fn mark_outcome(&self, is_yes: bool, won: bool) -> Result<()> {
+        match self.market.outcome_metadata {
+            OutcomeMetadata::Metaplex => self.rename_metaplex(is_yes, won),
+            OutcomeMetadata::TokenExtensions => self.rename_token_metadata(is_yes, won),
+        }
+    }
+
+    fn rename_token_metadata(&self, is_yes: bool, won: bool) -> Result<()> {
+        let mint = match is_yes {
+            true => self.mint_yes.to_account_info(),
+            false => self.mint_no.to_account_info(),
+        };
+
+        let (name, new_len) = {
+            let data = mint.try_borrow_data()?;
+            let state = StateWithExtensions::<SplMint>::unpack(&data)?;
+            let metadata = state.get_variable_len_extension::<TokenMetadata>()?;
+            let name = settled_token_name(&metadata.name, won);
+            let new_len = (data.len() + name.len()).saturating_sub(metadata.name.len());
+            (name, new_len)
+        };
+
+        // Token-2022 reallocs the mint for a longer name but leaves the rent
+        // to the caller.
+        let shortfall = Rent::get()?
+            .minimum_balance(new_len)
+            .saturating_sub(mint.lamports());
+        if shortfall > 0 {
+            transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.authority.to_account_info(),
+                        to: mint.clone(),
+                    },
+                ),
+                shortfall,
+            )?;
+        }
+
+        let seeds = &[
+            &b"market"[..],
+            &self.market.seed.to_le_bytes(),
+            &[self.market.market_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        token_metadata_update_field(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TokenMetadataUpdateField {
+                    token_program_id: self.token_program.to_account_info(),
+                    metadata: mint,
+                    update_authority: self.market.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            Field::Name,
+            name,
+        )
+    }
+
+    #[cfg(feature = "metaplex")]
+    fn rename_metaplex(&self, is_yes: bool, won: bool) -> Result<()> {
+        let (mint, metadata) = match is_yes {
+            true => (&self.mint_yes, &self.metadata_yes),
+            false => (&self.mint_no, &self.metadata_no),
+        };
+        let (Some(metadata_program), Some(metadata)) = (&self.token_metadata_program, metadata)
+        else {
+            return err!(MarketError::MissingMetadataAccounts);
+        };
+        require_keys_eq!(
+            metadata_program.key(),
+            Metadata::id(),
+            ErrorCode::InvalidProgramId
+        );
+        require_keys_eq!(
+            metadata.key(),
+            metadata_address(&mint.key()),
+            MarketError::InvalidMetadataAddress
+        );
+
+        let current = MetadataState::safe_deserialize(&metadata.try_borrow_data()?)?;
+        let data = DataV2 {
+            name: settled_token_name(&current.name, won),
+            symbol: current.symbol.trim_end_matches('\0').to_string(),
+            uri: current.uri.trim_end_matches('\0').to_string(),
+            seller_fee_basis_points: current.seller_fee_basis_points,
+            creators: current.creators,
+            collection: current.collection,
+            uses: current.uses,
+        };
+
+        let seeds = &[
+            &b"market"[..],
+            &self.market.seed.to_le_bytes(),
+            &[self.market.market_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(
+            metadata_program.to_account_info(),
+            UpdateMetadataAccountsV2 {
+                metadata: metadata.to_account_info(),
+                update_authority: self.market.to_account_info(),
+            },
+            signer_seeds,
+        );
+
+        update_metadata_accounts_v2(ctx, None, Some(data), None, None)
+    }
+
+    #[cfg(not(feature = "metaplex"))]
+    fn rename_metaplex(&self, _is_yes: bool, _won: bool) -> Result<()> {
+        err!(MarketError::MetaplexUnavailable)
+    }
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FreezeLosingAccount<'info> {
+    authority: Signer<'info>,
+    #[account(
+        has_one = authority,
+        seeds = [b"market", market.seed.to_le_bytes().as_ref()],
+        bump = market.market_bump,
+    )]
+    pub market: Box<Account<'info, Market>>,
+    #[account(
+        mint::token_program = token_program,
+        mint::freeze_authority = market,
+    )]
+    mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+    )]
+    token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FreezeLosingAccount<'info> {
+    /// Halts transfers of a settled market's losing outcome out of
+    /// `token_account`, so worthless tokens stop changing hands. The market's
+    /// own vaults stay unfrozen for `close_market`.
+    pub fn freeze_losing_account(&self) -> Result<LosingAccountFrozen> {
+        require!(self.market.settled, MarketError::MarketNotSettled);
+
+        let losing_mint = match self.market.winner {
+            Some(true) => self.market.mint_no,
+            Some(false) => self.market.mint_yes,
+            None => return err!(MarketError::WinnerRequired),
+        };
+        require_keys_eq!(self.mint.key(), losing_mint, MarketError::NotLosingAccount);
+        require_keys_neq!(
+            self.token_account.owner,
+            self.market.key(),
+            MarketError::NotLosingAccount
+        );
+
+        let seeds = &[
+            &b"market"[..],
+            &self.market.seed.to_le_bytes(),
+            &[self.market.market_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            FreezeAccount {
+                account: self.token_account.to_account_info(),
+                mint: self.mint.to_account_info(),
+                authority: self.market.to_account_info(),
+            },
+            signer_seeds,
+        );
+
+        freeze_account(ctx)?;
+
+        Ok(LosingAccountFrozen {
+            market: self.market.key(),
+            mint: self.mint.key(),
+            token_account: self.token_account.key(),
+            owner: self.token_account.owner,
+        })
+    }
+}
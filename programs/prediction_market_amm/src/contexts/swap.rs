@@ -1,14 +1,34 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
 };
 
 use crate::{
-    assert_non_zero, assert_not_expired, assert_not_locked, error::MarketError,
-    helpers::calculate_lmsr_output, states::Market,
+    assert_non_zero, assert_not_expired, assert_not_locked,
+    error::MarketError,
+    events::{PairsMerged, SideSwitched, Swapped},
+    helpers::{
+        is_native_mint, payout_account, price_to_u64, quote_lmsr_swap, quote_switch,
+        trade_to_price_input, unwrap_sol, wrap_sol, LMSRCalculator, LimitOrder, PRECISION,
+        TARGET_PRICE_TOLERANCE, UNWRAP_SEED,
+    },
+    states::{AllowlistEntry, Market, Position, PriceHistory},
 };
 
+const PRICE_SCALE: u64 = 10u64.pow(PRECISION);
+
+/// What a trade did: filled, switched sides or merged pairs.
+pub enum SwapOutcome {
+    Filled(Swapped),
+    Switched(SideSwitched),
+    Merged(PairsMerged),
+}
+
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Swap<'info> {
     #[account(mut)]
@@ -26,25 +46,28 @@ pub struct Swap<'info> {
     )]
     mint_no: Box<InterfaceAccount<'info, Mint>>,
     #[account(
-        mint::token_program = token_program,
+        mint::token_program = collateral_token_program,
     )]
     mint_usdc: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         associated_token::mint = mint_yes,
         associated_token::authority = market,
+        associated_token::token_program = token_program,
     )]
     vault_yes: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_no,
         associated_token::authority = market,
+        associated_token::token_program = token_program,
     )]
     vault_no: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_usdc,
-        associated_token::authority = market
+        associated_token::authority = market,
+        associated_token::token_program = collateral_token_program,
     )]
     vault_usdc: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
@@ -52,6 +75,7 @@ pub struct Swap<'info> {
         payer = user,
         associated_token::mint = mint_yes,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
     user_ata_yes: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
@@ -59,29 +83,75 @@ pub struct Swap<'info> {
         payer = user,
         associated_token::mint = mint_no,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
     user_ata_no: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Wrapped SOL markets can omit it: buys wrap lamports straight into
+    /// the vault and proceeds go through `unwrap_account`.
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_usdc,
         associated_token::authority = user,
+        associated_token::token_program = collateral_token_program,
     )]
-    user_ata_usdc: Box<InterfaceAccount<'info, TokenAccount>>,
+    user_ata_usdc: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// Temporary wrapped SOL account that proceeds pass through on their way
+    /// to the user's lamports; closed again before the instruction returns.
+    #[account(
+        init,
+        payer = user,
+        seeds = [UNWRAP_SEED, user.key().as_ref()],
+        bump,
+        token::mint = mint_usdc,
+        token::authority = user,
+        token::token_program = collateral_token_program,
+    )]
+    unwrap_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     #[account(
         mut,
         has_one = mint_yes,
         has_one = mint_no,
+        has_one = mint_usdc,
         seeds = [b"market", market.seed.to_le_bytes().as_ref()],
         bump = market.market_bump,
     )]
     pub market: Box<Account<'info, Market>>,
+    #[account(
+        mut,
+        has_one = market,
+        seeds = [b"price_history", market.key().as_ref()],
+        bump = price_history.bump,
+    )]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+    /// Opened with `open_position`; required while the market caps user
+    /// exposure.
+    #[account(
+        mut,
+        has_one = market,
+        has_one = user,
+        seeds = [b"position", market.key().as_ref(), user.key().as_ref()],
+        bump = position.bump,
+    )]
+    pub position: Option<Box<Account<'info, Position>>>,
+    #[account(
+        has_one = market,
+        has_one = user,
+        seeds = [b"allowlist", market.key().as_ref(), user.key().as_ref()],
+        bump = allowlist_entry.bump,
+    )]
+    pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
 
     pub token_program: Interface<'info, TokenInterface>,
+    /// Owns the collateral mint; differs from `token_program` when the
+    /// outcome mints use Token-2022 but the collateral does not.
+    pub collateral_token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Swap<'info> {
+    #[allow(clippy::too_many_arguments)]
     pub fn swap(
         &mut self,
         is_buying: bool,
@@ -89,40 +159,426 @@ impl<'info> Swap<'info> {
         is_yes: bool,
         min_out: u64,
         expiration: i64,
-    ) -> Result<()> {
+        proof: &[[u8; 32]],
+        limit: Option<LimitOrder>,
+    ) -> Result<SwapOutcome> {
         assert_not_locked!(self.market.locked);
         assert_not_expired!(expiration);
         assert_non_zero!([amount_in, min_out]);
+        self.market
+            .check_trading_open(Clock::get()?.unix_timestamp)?;
+
+        self.market
+            .check_access(&self.user.key(), self.allowlist_entry.is_some(), proof)?;
 
-        let amount_out = calculate_lmsr_output(
+        // Unfilled collateral or shares stay with the user.
+        let amount_in = match limit {
+            Some(order) if order.partial_fill => order.max_fill(
+                self.market.liquidity()?,
+                self.market.fee,
+                amount_in,
+                self.vault_yes.amount,
+                self.vault_no.amount,
+                is_buying,
+                is_yes,
+            )?,
+            _ => amount_in,
+        };
+        require!(amount_in > 0, MarketError::LimitPriceExceeded);
+
+        let quote = quote_lmsr_swap(
+            self.market.liquidity()?,
+            self.market.fee,
             amount_in,
             self.vault_yes.amount,
             self.vault_no.amount,
             is_buying,
             is_yes,
         )?;
+        let amount_out = quote.amount_out;
+
+        if let Some(order) = limit {
+            require!(
+                order.within(is_buying, quote.average_price),
+                MarketError::LimitPriceExceeded
+            );
+        }
 
         require!(amount_out >= min_out, MarketError::SlippageExceeded);
+        self.market.limits.check_trade_size(amount_in)?;
+
+        let clock = Clock::get()?;
+        self.check_circuit_breaker(&clock, is_yes, quote.price_before, quote.price_after)?;
 
         if is_buying {
             self.deposit_tokens(true, None, amount_in)?;
-            self.withdraw_token(false, amount_out, Some(is_yes))
+            self.withdraw_token(false, amount_out, Some(is_yes))?;
         } else {
             self.deposit_tokens(false, Some(is_yes), amount_in)?;
-            self.withdraw_token(true, amount_out, None)
+            self.withdraw_token(true, amount_out, None)?;
+        }
+
+        self.record_price(&clock)?;
+
+        self.record_trade(is_buying, is_yes, amount_in, amount_out, quote.fee)?;
+
+        if is_buying {
+            self.check_user_exposure()?;
+            self.market
+                .limits
+                .check_open_interest(self.open_interest()?)?;
+        }
+
+        self.unwrap_sol()?;
+
+        Ok(SwapOutcome::Filled(Swapped {
+            market: self.market.key(),
+            user: self.user.key(),
+            is_buying,
+            is_yes,
+            amount_in,
+            amount_out,
+            fee: quote.fee,
+            price_before: quote.price_before,
+            price_after: quote.price_after,
+        }))
+    }
+
+    /// Buys or sells `is_yes` shares until the outcome's price reaches
+    /// `target_price`. `max_spend` caps the collateral paid by a buy or the
+    /// shares given up by a sell. Gated markets need an allowlist entry.
+    pub fn trade_to_price(
+        &mut self,
+        target_price: u64,
+        is_yes: bool,
+        max_spend: u64,
+    ) -> Result<SwapOutcome> {
+        let (b, fee_bps) = (self.market.liquidity()?, self.market.fee);
+        let (vault_yes, vault_no) = (self.vault_yes.amount, self.vault_no.amount);
+
+        let (is_buying, amount_in) =
+            trade_to_price_input(b, fee_bps, vault_yes, vault_no, target_price, is_yes)?;
+
+        require!(amount_in > 0, MarketError::InvalidPrice);
+        require!(amount_in <= max_spend, MarketError::SlippageExceeded);
+
+        let quote = quote_lmsr_swap(
+            b,
+            fee_bps,
+            amount_in,
+            vault_yes,
+            vault_no,
+            is_buying,
+            is_yes,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let outcome = self.swap(
+            is_buying,
+            amount_in,
+            is_yes,
+            quote.amount_out,
+            now,
+            &[],
+            None,
+        )?;
+
+        if let SwapOutcome::Filled(swapped) = &outcome {
+            require!(
+                swapped.price_after.abs_diff(target_price) <= TARGET_PRICE_TOLERANCE,
+                MarketError::TargetPriceMissed
+            );
+        }
+
+        Ok(outcome)
+    }
+
+    /// Sells `amount` of one outcome and buys the other with the proceeds in
+    /// a single LMSR transition, paying the fee once. No collateral moves.
+    /// Gated markets need an allowlist entry.
+    pub fn switch_side(
+        &mut self,
+        amount: u64,
+        from_yes: bool,
+        min_out: u64,
+    ) -> Result<SwapOutcome> {
+        assert_not_locked!(self.market.locked);
+        assert_non_zero!([amount, min_out]);
+        self.market
+            .check_trading_open(Clock::get()?.unix_timestamp)?;
+
+        self.market
+            .check_access(&self.user.key(), self.allowlist_entry.is_some(), &[])?;
+
+        let to_yes = !from_yes;
+        let quote = quote_switch(
+            self.market.liquidity()?,
+            self.market.fee,
+            amount,
+            self.vault_yes.amount,
+            self.vault_no.amount,
+            from_yes,
+        )?;
+
+        require!(quote.amount_out >= min_out, MarketError::SlippageExceeded);
+        self.market.limits.check_trade_size(quote.collateral)?;
+
+        let clock = Clock::get()?;
+        self.check_circuit_breaker(&clock, to_yes, quote.price_before, quote.price_after)?;
+
+        self.deposit_tokens(false, Some(from_yes), amount)?;
+        self.withdraw_token(false, quote.amount_out, Some(to_yes))?;
+
+        self.record_price(&clock)?;
+
+        // Booked as a sell and a buy of the same collateral, so exposure is
+        // unchanged and the fee is only counted once.
+        self.record_trade(false, from_yes, amount, quote.collateral, quote.fee)?;
+        self.record_trade(true, to_yes, quote.collateral, quote.amount_out, 0)?;
+
+        self.market
+            .limits
+            .check_open_interest(self.open_interest()?)?;
+
+        // Nothing is paid out, but a passed unwrap account was still opened.
+        self.unwrap_sol()?;
+
+        Ok(SwapOutcome::Switched(SideSwitched {
+            market: self.market.key(),
+            user: self.user.key(),
+            from_yes,
+            amount_in: amount,
+            amount_out: quote.amount_out,
+            collateral: quote.collateral,
+            fee: quote.fee,
+            price_before: quote.price_before,
+            price_after: quote.price_after,
+        }))
+    }
+
+    /// Cashes out the user's whole `is_yes` holding before settlement.
+    /// Matched YES+NO pairs are first returned to the vaults for one unit of
+    /// collateral each, which leaves prices unchanged. `min_out` bounds the
+    /// total collateral received. Gated markets need an allowlist entry.
+    pub fn close_position(
+        &mut self,
+        is_yes: bool,
+        min_out: u64,
+        close_ata: bool,
+    ) -> Result<Vec<SwapOutcome>> {
+        assert_not_locked!(self.market.locked);
+        self.market
+            .check_trading_open(Clock::get()?.unix_timestamp)?;
+
+        let mut outcomes = Vec::new();
+        let mut collateral_out = 0u64;
+
+        let mut balance = match is_yes {
+            true => self.user_ata_yes.amount,
+            false => self.user_ata_no.amount,
+        };
+
+        let pairs = self.user_ata_yes.amount.min(self.user_ata_no.amount);
+        if pairs > 0 {
+            self.market
+                .check_access(&self.user.key(), self.allowlist_entry.is_some(), &[])?;
+
+            self.deposit_tokens(false, Some(true), pairs)?;
+            self.deposit_tokens(false, Some(false), pairs)?;
+            self.withdraw_token(true, pairs, None)?;
+
+            self.record_trade(false, true, pairs, pairs, 0)?;
+            self.record_trade(false, false, pairs, 0, 0)?;
+
+            let clock = Clock::get()?;
+            self.record_price(&clock)?;
+
+            balance -= pairs;
+            collateral_out = pairs;
+            outcomes.push(SwapOutcome::Merged(PairsMerged {
+                market: self.market.key(),
+                user: self.user.key(),
+                pairs,
+            }));
+        }
+
+        if balance > 0 {
+            let now = Clock::get()?.unix_timestamp;
+            let outcome = self.swap(false, balance, is_yes, 1, now, &[], None)?;
+
+            let sold = match &outcome {
+                SwapOutcome::Filled(swapped) => swapped.amount_out,
+                _ => 0,
+            };
+            outcomes.push(outcome);
+
+            collateral_out = collateral_out
+                .checked_add(sold)
+                .ok_or(MarketError::MathOverflow)?;
+        }
+
+        require!(collateral_out > 0, MarketError::ZeroBalance);
+        require!(collateral_out >= min_out, MarketError::SlippageExceeded);
+
+        if close_ata {
+            self.close_user_ata(is_yes)?;
+        }
+        self.unwrap_sol()?;
+
+        Ok(outcomes)
+    }
+
+    /// Refunds the rent of the user's emptied outcome token account.
+    fn close_user_ata(&self, is_yes: bool) -> Result<()> {
+        let account = match is_yes {
+            true => self.user_ata_yes.to_account_info(),
+            false => self.user_ata_no.to_account_info(),
+        };
+
+        let accounts = CloseAccount {
+            account,
+            destination: self.user.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(self.token_program.to_account_info(), accounts);
+
+        close_account(ctx)
+    }
+
+    /// Pays the proceeds collected in `unwrap_account` out as lamports. Does
+    /// nothing if it was not passed or was already closed in this instruction.
+    fn unwrap_sol(&self) -> Result<()> {
+        let Some(unwrap_account) = &self.unwrap_account else {
+            return Ok(());
+        };
+        if unwrap_account.to_account_info().lamports() == 0 {
+            return Ok(());
+        }
+
+        unwrap_sol(
+            unwrap_account.to_account_info(),
+            self.user.to_account_info(),
+            self.collateral_token_program.to_account_info(),
+        )
+    }
+
+    /// Books a trade on the user's position when they opened one.
+    fn record_trade(
+        &mut self,
+        is_buying: bool,
+        is_yes: bool,
+        amount_in: u64,
+        amount_out: u64,
+        fee: u64,
+    ) -> Result<()> {
+        match self.position.as_mut() {
+            Some(position) => position.record_trade(is_buying, is_yes, amount_in, amount_out, fee),
+            None => Ok(()),
         }
     }
 
+    /// Exposure is tracked on the position, so capped markets need one.
+    fn check_user_exposure(&self) -> Result<()> {
+        if self.market.limits.max_user_exposure == 0 {
+            return Ok(());
+        }
+        let position = self
+            .position
+            .as_ref()
+            .ok_or(MarketError::PositionRequired)?;
+        self.market
+            .limits
+            .check_user_exposure(position.net_exposure())
+    }
+
+    /// Rejects the trade if moving the `is_yes` price from `price_before` to
+    /// `price_after` breaks the circuit breaker. Once its window has passed,
+    /// the reference moves to the time-weighted price since it was last
+    /// taken, so a price pushed just before the window closed does not become
+    /// the next reference.
+    fn check_circuit_breaker(
+        &mut self,
+        clock: &Clock,
+        is_yes: bool,
+        price_before: u64,
+        price_after: u64,
+    ) -> Result<()> {
+        let (yes_before, yes_after) = match is_yes {
+            true => (price_before, price_after),
+            false => (
+                PRICE_SCALE.saturating_sub(price_before),
+                PRICE_SCALE.saturating_sub(price_after),
+            ),
+        };
+
+        if self.market.breaker_window_elapsed(clock.slot) {
+            let since = clock
+                .unix_timestamp
+                .saturating_sub(self.market.reference_timestamp);
+            // Without history to average over, the current price will do.
+            let reference = self
+                .price_history
+                .twap(since, clock.unix_timestamp)
+                .unwrap_or(yes_before);
+            self.market
+                .roll_reference(reference, clock.slot, clock.unix_timestamp);
+        }
+
+        self.market.check_circuit_breaker(yes_before, yes_after)
+    }
+
+    /// Every fill ends here, so this also opens trading for good.
+    fn record_price(&mut self, clock: &Clock) -> Result<()> {
+        self.market.trading_opened = true;
+
+        self.vault_yes.reload()?;
+        self.vault_no.reload()?;
+
+        let price_yes = self.current_price(true)?;
+        self.price_history
+            .record(clock.unix_timestamp, clock.slot, price_yes)
+    }
+
+    /// Outcome tokens held by traders rather than the vaults.
+    pub fn open_interest(&self) -> Result<u64> {
+        let yes = self.mint_yes.supply.saturating_sub(self.vault_yes.amount);
+        let no = self.mint_no.supply.saturating_sub(self.vault_no.amount);
+        Ok(yes.checked_add(no).ok_or(MarketError::MathOverflow)?)
+    }
+
+    pub fn current_price(&self, is_yes: bool) -> Result<u64> {
+        let calculator = LMSRCalculator::new(
+            self.market.liquidity()?,
+            self.vault_yes.amount,
+            self.vault_no.amount,
+        );
+        price_to_u64(calculator.calculate_price(is_yes)?)
+    }
+
     pub fn deposit_tokens(
         &mut self,
         is_usdc: bool,
         is_yes: Option<bool>,
         amount: u64,
     ) -> Result<()> {
+        if is_usdc && is_native_mint(&self.mint_usdc.key()) {
+            return wrap_sol(
+                self.user.to_account_info(),
+                self.vault_usdc.to_account_info(),
+                self.system_program.to_account_info(),
+                self.collateral_token_program.to_account_info(),
+                amount,
+            );
+        }
+
         let (mint, from, to, decimals) = match is_usdc {
             true => (
                 self.mint_usdc.to_account_info(),
-                self.user_ata_usdc.to_account_info(),
+                self.user_ata_usdc
+                    .as_ref()
+                    .ok_or(MarketError::MissingCollateralAccount)?
+                    .to_account_info(),
                 self.vault_usdc.to_account_info(),
                 self.mint_usdc.decimals,
             ),
@@ -143,6 +599,11 @@ impl<'info> Swap<'info> {
             },
         };
 
+        let token_program = match is_usdc {
+            true => self.collateral_token_program.to_account_info(),
+            false => self.token_program.to_account_info(),
+        };
+
         let account = TransferChecked {
             from,
             mint,
@@ -150,7 +611,7 @@ impl<'info> Swap<'info> {
             authority: self.user.to_account_info(),
         };
 
-        let ctx = CpiContext::new(self.token_program.to_account_info(), account);
+        let ctx = CpiContext::new(token_program, account);
 
         transfer_checked(ctx, amount, decimals)
     }
@@ -165,7 +626,11 @@ impl<'info> Swap<'info> {
             true => (
                 self.mint_usdc.to_account_info(),
                 self.vault_usdc.to_account_info(),
-                self.user_ata_usdc.to_account_info(),
+                payout_account(
+                    &self.mint_usdc.key(),
+                    self.unwrap_account.as_ref().map(|a| a.to_account_info()),
+                    self.user_ata_usdc.as_ref().map(|a| a.to_account_info()),
+                )?,
                 self.mint_usdc.decimals,
             ),
             false => match is_yes {
@@ -185,6 +650,11 @@ impl<'info> Swap<'info> {
             },
         };
 
+        let token_program = match is_usdc {
+            true => self.collateral_token_program.to_account_info(),
+            false => self.token_program.to_account_info(),
+        };
+
         let account = TransferChecked {
             from,
             mint,
@@ -199,11 +669,7 @@ impl<'info> Swap<'info> {
         ];
         let signer_seeds = &[&seeds[..]];
 
-        let ctx = CpiContext::new_with_signer(
-            self.token_program.to_account_info(),
-            account,
-            signer_seeds,
-        );
+        let ctx = CpiContext::new_with_signer(token_program, account, signer_seeds);
 
         transfer_checked(ctx, amount, decimals)
     }
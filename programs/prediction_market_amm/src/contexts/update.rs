@@ -1,13 +1,18 @@
 use anchor_lang::prelude::*;
 
-use crate::states::Market;
+use crate::{
+    events::{MarketLocked, MarketUnlocked},
+    states::Market,
+};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Update<'info> {
     #[account(mut)]
-    signer: Signer<'info>,
+    authority: Signer<'info>,
     #[account(
         mut,
+        has_one = authority,
         seeds = [b"market", market.seed.to_le_bytes().as_ref()],
         bump = market.market_bump,
     )]
@@ -15,13 +20,17 @@ pub struct Update<'info> {
 }
 
 impl<'info> Update<'info> {
-    pub fn lock(&mut self) -> Result<()> {
+    pub fn lock(&mut self) -> Result<MarketLocked> {
         self.market.locked = true;
-        Ok(())
+        Ok(MarketLocked {
+            market: self.market.key(),
+        })
     }
 
-    pub fn unlock(&mut self) -> Result<()> {
+    pub fn unlock(&mut self) -> Result<MarketUnlocked> {
         self.market.locked = false;
-        Ok(())
+        Ok(MarketUnlocked {
+            market: self.market.key(),
+        })
     }
 }
\ No newline at end of file
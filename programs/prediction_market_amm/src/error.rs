@@ -24,7 +24,7 @@ pub enum MarketError {
     BumpError,
     #[msg("Curve error.")]
     CurveError,
-    #[msg("Fee is greater than 100%")]
+    #[msg("Fee exceeds the maximum allowed")]
     InvalidFee,
     #[msg("Invalid update authority")]
     InvalidAuthority,
@@ -39,7 +39,7 @@ pub enum MarketError {
     #[msg("Zero balance.")]
     ZeroBalance,
     #[msg("Market already settled")]
-    MarketAlreadySettled,
+    MarketSettled,
     #[msg("Market not settled")]
     MarketNotSettled,
     #[msg("Not authorized to perform this")]
@@ -56,4 +56,84 @@ pub enum MarketError {
     InvalidPrice,
     #[msg("Invalid cost")]
     InvalidCost,
+    #[msg("Invalid remaining accounts")]
+    InvalidRemainingAccounts,
+    #[msg("Invalid market account")]
+    InvalidMarket,
+    #[msg("Keeper tip exceeds the maximum allowed")]
+    KeeperTipTooHigh,
+    #[msg("Market is not an approved delegate of the holder's tokens")]
+    NotDelegated,
+    #[msg("Winning tokens are still outstanding")]
+    UnclaimedWinnings,
+    #[msg("Not enough price history for the requested window")]
+    InsufficientPriceHistory,
+    #[msg("Invalid TWAP window")]
+    InvalidTwapWindow,
+    #[msg("Invalid return data")]
+    InvalidReturnData,
+    #[msg("Trade size exceeds the market limit")]
+    TradeSizeExceeded,
+    #[msg("Position exceeds the per-user exposure limit")]
+    ExposureLimitExceeded,
+    #[msg("Trade exceeds the market open interest limit")]
+    OpenInterestLimitExceeded,
+    #[msg("Wallet is not allowed to trade in this market")]
+    AccessDenied,
+    #[msg("Fill price is outside the limit price")]
+    LimitPriceExceeded,
+    #[msg("End time must be in the future")]
+    InvalidEndTime,
+    #[msg("Market name is too long")]
+    MarketNameTooLong,
+    #[msg("Token name is too long")]
+    TokenNameTooLong,
+    #[msg("Token symbol is too long")]
+    TokenSymbolTooLong,
+    #[msg("Token URI is too long")]
+    TokenUriTooLong,
+    #[msg("Vault is not the market's associated token account")]
+    InvalidVaultAddress,
+    #[msg("Metadata account does not match the mint")]
+    InvalidMetadataAddress,
+    #[msg("Metaplex metadata is not supported by this deployment")]
+    MetaplexUnavailable,
+    #[msg("Metadata accounts are required for Metaplex outcome tokens")]
+    MissingMetadataAccounts,
+    #[msg("Token extension metadata requires the Token-2022 program")]
+    Token2022Required,
+    #[msg("Collateral mint has too many decimals")]
+    UnsupportedCollateralDecimals,
+    #[msg("Market info field exceeds its maximum length")]
+    MarketInfoTooLong,
+    #[msg("Market info can only change before trading opens")]
+    TradingAlreadyOpen,
+    #[msg("A collateral token account is required for this market")]
+    MissingCollateralAccount,
+    #[msg("A resolved market must name its winning outcome")]
+    WinnerRequired,
+    #[msg("Only the winning outcome can be claimed")]
+    LosingOutcome,
+    #[msg("Only holders of the losing outcome can be frozen")]
+    NotLosingAccount,
+    #[msg("This market caps exposure, which needs an open position")]
+    PositionRequired,
+    #[msg("Position does not belong to this holder")]
+    InvalidPosition,
+    #[msg("The claimed outcome's token account is missing")]
+    MissingOutcomeAccount,
+    #[msg("Position can only be closed once claimed or after the market closes")]
+    PositionOpen,
+    #[msg("Trade did not reach the target price")]
+    TargetPriceMissed,
+    #[msg("Observation interval must be at least one second")]
+    InvalidObservationInterval,
+    #[msg("Deposits must add as many YES as NO tokens")]
+    UnbalancedDeposit,
+    #[msg("Trade moves the price past the circuit breaker")]
+    CircuitBreakerTripped,
+    #[msg("Trading has ended for this market")]
+    MarketEnded,
+    #[msg("Vault collateral does not cover the winning tokens")]
+    InsufficientCollateral,
 }
@@ -0,0 +1,145 @@
+use anchor_lang::prelude::*;
+
+use crate::states::MarketTag;
+
+// Field order is relied on by downstream indexers: only append new fields.
+// Prices are probabilities scaled by 10^PRECISION.
+
+#[event]
+pub struct MarketCreated {
+    pub market: Pubkey,
+    pub seed: u64,
+    pub market_name: String,
+    pub mint_yes: Pubkey,
+    pub mint_no: Pubkey,
+    pub mint_usdc: Pubkey,
+    pub fee: u16,
+    pub end_time: i64,
+    pub keeper_tip_bps: u16,
+    pub initial_price: u64,
+    pub subsidy: u64,
+    pub collateral_decimals: u8,
+}
+
+#[event]
+pub struct LiquidityAdded {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub amount_yes: u64,
+    pub amount_no: u64,
+}
+
+#[event]
+pub struct Swapped {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub is_buying: bool,
+    pub is_yes: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// In collateral base units.
+    pub fee: u64,
+    pub price_before: u64,
+    pub price_after: u64,
+}
+
+#[event]
+pub struct MarketLocked {
+    pub market: Pubkey,
+}
+
+#[event]
+pub struct MarketUnlocked {
+    pub market: Pubkey,
+}
+
+#[event]
+pub struct MarketSettled {
+    pub market: Pubkey,
+    pub settled: bool,
+    pub settled_at: i64,
+    pub winner: Option<bool>,
+}
+
+#[event]
+pub struct Claimed {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub is_yes: bool,
+    pub amount_burned: u64,
+    pub payout: u64,
+    pub keeper_tip: u64,
+}
+
+#[event]
+pub struct MarketClosed {
+    pub market: Pubkey,
+    /// Collateral left in `vault_usdc` after every winner was paid, sent to
+    /// the treasury.
+    pub dust_swept: u64,
+    pub rent_recipient: Pubkey,
+}
+
+#[event]
+pub struct AccessRootUpdated {
+    pub market: Pubkey,
+    pub access_root: Option<[u8; 32]>,
+}
+
+#[event]
+pub struct AccessGranted {
+    pub market: Pubkey,
+    pub user: Pubkey,
+}
+
+#[event]
+pub struct AccessRevoked {
+    pub market: Pubkey,
+    pub user: Pubkey,
+}
+
+#[event]
+pub struct SideSwitched {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub from_yes: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub collateral: u64,
+    /// In collateral base units.
+    pub fee: u64,
+    pub price_before: u64,
+    pub price_after: u64,
+}
+
+#[event]
+pub struct PairsMerged {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub pairs: u64,
+}
+
+#[event]
+pub struct MarketInfoUpdated {
+    pub market: Pubkey,
+    pub market_info: Pubkey,
+    pub title: String,
+    pub category: MarketTag,
+    pub rules_hash: [u8; 32],
+}
+
+#[event]
+pub struct LosingAccountFrozen {
+    pub market: Pubkey,
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub owner: Pubkey,
+}
+
+#[event]
+pub struct CrankApproved {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub is_yes: bool,
+    pub amount: u64,
+}
@@ -2,7 +2,7 @@
 macro_rules! assert_non_zero {
     ($array:expr) => {
         if $array.contains(&0u64) {
-            return err!(crate::error::MarketError::ZeroBalance)
+            return err!($crate::error::MarketError::ZeroBalance)
         }
     };
 }
@@ -11,7 +11,7 @@ macro_rules! assert_non_zero {
 macro_rules! assert_not_locked {
     ($lock:expr) => {
         if $lock == true {
-            return err!(crate::error::MarketError::PoolLocked)
+            return err!($crate::error::MarketError::PoolLocked)
         }
     };
 }
@@ -20,7 +20,7 @@ macro_rules! assert_not_locked {
 macro_rules! assert_not_expired {
     ($expiration:expr) => {
         if Clock::get()?.unix_timestamp > $expiration {
-            return err!(crate::error::MarketError::OfferExpired);
+            return err!($crate::error::MarketError::OfferExpired);
         }
     };
 }
\ No newline at end of file
@@ -1,4 +1,7 @@
 pub mod macros;
 
 pub mod utils;
-pub use utils::*;
\ No newline at end of file
+pub use utils::*;
+
+pub mod wsol;
+pub use wsol::*;
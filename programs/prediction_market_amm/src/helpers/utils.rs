@@ -1,13 +1,40 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, solana_program::keccak::hashv};
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use rust_decimal::prelude::*;
 
-use crate::error::MarketError;
+use crate::{error::MarketError, states::Observation};
 
 pub const PRECISION: u32 = 6;
+/// LMSR liquidity `b` in base units of a 6-decimal collateral; see
+/// [`liquidity_for_decimals`].
 pub const DEFAULT_B: u64 = 1_000_000_000;
+pub const DEFAULT_COLLATERAL_DECIMALS: u8 = 6;
+pub const MAX_COLLATERAL_DECIMALS: u8 = 12;
+pub const BPS_DENOMINATOR: u16 = 10_000;
+pub const MAX_KEEPER_TIP_BPS: u16 = 500;
+/// How far, scaled by 10^PRECISION, `trade_to_price` may land from its target.
+pub const TARGET_PRICE_TOLERANCE: u64 = 100;
+pub const MAX_FEE_BPS: u16 = 1_000;
+/// Outcome tokens, in multiples of `b`, that both vaults start with on top of
+/// the offset setting the initial price. Ten `b` lets trades reach within
+/// e^−10 of either bound before anyone adds liquidity.
+pub const INITIAL_VAULT_DEPTH: u64 = 10;
+/// Matches `#[max_len]` on `Market::market_name`.
+pub const MAX_MARKET_NAME_LEN: usize = 32;
+// Metaplex metadata limits.
+pub const MAX_TOKEN_NAME_LEN: usize = 32;
+pub const MAX_TOKEN_SYMBOL_LEN: usize = 10;
+pub const MAX_TOKEN_URI_LEN: usize = 200;
 
+/// ln 2, for range reduction in [`LMSRCalculator::ln`].
+const LN_2: Decimal = dec!(0.6931471805599453094172321215);
+/// Largest exponent whose power of e fits a `Decimal`.
+const MAX_EXP: Decimal = dec!(64);
+
+/// LMSR over a market's vault balances. The market maker's outstanding
+/// shares of an outcome are the negated vault balance, so an outcome gets
+/// dearer as its vault empties: p_yes = 1/(1 + e^((yes−no)/b)).
 #[derive(Debug)]
 pub struct LMSRCalculator {
     pub b: Decimal,
@@ -24,95 +51,935 @@ impl LMSRCalculator {
         }
     }
 
+    /// Collateral that takes `shares` of an outcome out of its vault:
+    /// C(q + s) − C(q) = s + b·ln(p + (1−p)·e^(−s/b)), rounded up.
     pub fn calculate_cost_to_buy(&self, shares: u64, is_yes: bool) -> Result<u64> {
-        let current_cost = self.calculate_cost()?;
-        let shares_decimal = Decimal::from(shares);
-        
-        let new_yes_shares = if is_yes {
-            self.yes_shares + shares_decimal
-        } else {
-            self.yes_shares
-        };
-        
-        let new_no_shares = if !is_yes {
-            self.no_shares + shares_decimal
-        } else {
-            self.no_shares
-        };
+        let price = self.calculate_price(is_yes)?;
+        let shares = Decimal::from(shares);
+        let scaled = self.exp(-shares / self.b)?;
+
+        let cost = shares + self.b * self.ln(price + (Decimal::ONE - price) * scaled)?;
+        Ok(cost.ceil().to_u64().ok_or(MarketError::MathOverflow)?)
+    }
 
-        let new_cost = Self {
-            b: self.b,
-            yes_shares: new_yes_shares,
-            no_shares: new_no_shares,
-        }.calculate_cost()?;
+    /// Collateral paid for putting `shares` of an outcome back into its
+    /// vault: C(q) − C(q − s) = −b·ln(p·e^(−s/b) + 1 − p), rounded down.
+    pub fn calculate_proceeds_from_sell(&self, shares: u64, is_yes: bool) -> Result<u64> {
+        let price = self.calculate_price(is_yes)?;
+        let scaled = self.exp(-Decimal::from(shares) / self.b)?;
 
-        let cost_difference = new_cost - current_cost;
-        Ok(cost_difference.round_dp(PRECISION).to_u64().ok_or(MarketError::MathOverflow)?)
+        let proceeds = -self.b * self.ln(price * scaled + Decimal::ONE - price)?;
+        Ok(proceeds
+            .max(Decimal::ZERO)
+            .floor()
+            .to_u64()
+            .ok_or(MarketError::MathOverflow)?)
     }
 
+    /// Shares of an outcome that `collateral` buys, inverting
+    /// [`Self::calculate_cost_to_buy`]: s = X + b·ln(e^(−X/b) + (1 − e^(−X/b))/p).
+    pub fn shares_for_collateral(&self, collateral: Decimal, is_yes: bool) -> Result<Decimal> {
+        let price = self.calculate_price(is_yes)?;
+        require!(price > Decimal::ZERO, MarketError::InvalidPrice);
+
+        let scaled = self.exp(-collateral / self.b)?;
+        Ok(collateral + self.b * self.ln(scaled + (Decimal::ONE - scaled) / price)?)
+    }
+
+    /// C(q) = b·ln(e^(−yes/b) + e^(−no/b)).
     pub fn calculate_cost(&self) -> Result<Decimal> {
-        let yes_term = self.exp(self.yes_shares / self.b)?;
-        let no_term = self.exp(self.no_shares / self.b)?;
-        
-        let sum = yes_term + no_term;
-        let result = self.ln(sum)?;
-        
-        Ok(result * self.b)
+        let yes_term = self.exp(-self.yes_shares / self.b)?;
+        let no_term = self.exp(-self.no_shares / self.b)?;
+
+        Ok(self.b * self.ln(yes_term + no_term)?)
     }
 
     pub fn calculate_price(&self, is_yes: bool) -> Result<Decimal> {
-        let yes_term = self.exp(self.yes_shares / self.b)?;
-        let no_term = self.exp(self.no_shares / self.b)?;
-        
-        let denominator = yes_term + no_term;
-        let numerator = if is_yes { yes_term } else { no_term };
-        
-        Ok(numerator / denominator)
+        let (traded, other) = match is_yes {
+            true => (self.yes_shares, self.no_shares),
+            false => (self.no_shares, self.yes_shares),
+        };
+
+        Ok(Decimal::ONE / (Decimal::ONE + self.exp((traded - other) / self.b)?))
     }
 
+    /// Vault balance of the traded outcome at which its price equals `price`:
+    /// q_other + b·ln((1−p)/p).
+    pub fn shares_at_price(&self, price: Decimal, is_yes: bool) -> Result<Decimal> {
+        require!(
+            price > Decimal::ZERO && price < Decimal::ONE,
+            MarketError::InvalidPrice
+        );
+
+        let other_shares = if is_yes { self.no_shares } else { self.yes_shares };
+        Ok(other_shares + self.b * self.ln((Decimal::ONE - price) / price)?)
+    }
+
+    /// Shares of the traded outcome to buy (positive) or sell (negative) to
+    /// move its price to `price`: b·(logit(p′) − logit(p)).
+    pub fn shares_to_price(&self, price: Decimal, is_yes: bool) -> Result<Decimal> {
+        let traded_shares = if is_yes { self.yes_shares } else { self.no_shares };
+        Ok(traded_shares - self.shares_at_price(price, is_yes)?)
+    }
+
+    /// Collateral, before fees, that buying up to `price` costs, or selling
+    /// down to it pays out: b·|ln((1−p)/(1−p′))|.
+    pub fn collateral_to_price(&self, price: Decimal, is_yes: bool) -> Result<Decimal> {
+        require!(
+            price > Decimal::ZERO && price < Decimal::ONE,
+            MarketError::InvalidPrice
+        );
+
+        let current = self.calculate_price(is_yes)?;
+        Ok((self.b * self.ln((Decimal::ONE - current) / (Decimal::ONE - price))?).abs())
+    }
+
+    /// Worst-case loss of the market maker from the current state: b·ln(1/p)
+    /// for the price p of the less likely outcome.
+    pub fn max_loss(&self) -> Result<Decimal> {
+        let price_yes = self.calculate_price(true)?;
+        let lowest = price_yes.min(Decimal::ONE - price_yes);
+        require!(lowest > Decimal::ZERO, MarketError::InvalidPrice);
+
+        Ok(self.b * self.ln(Decimal::ONE / lowest)?)
+    }
+
+    /// e^x, halving x until the series converges quickly and squaring back.
+    /// Underflows to zero and errors past [`MAX_EXP`].
     fn exp(&self, x: Decimal) -> Result<Decimal> {
-        let mut sum = dec!(1.0);
-        let mut term = dec!(1.0);
-        
-        for i in 1..=10 {
-            term = term * x / Decimal::from(i);
+        if x < -MAX_EXP {
+            return Ok(Decimal::ZERO);
+        }
+        require!(x <= MAX_EXP, MarketError::MathOverflow);
+        if x.is_sign_negative() {
+            return Ok(Decimal::ONE / self.exp(-x)?);
+        }
+
+        let mut reduced = x;
+        let mut halvings = 0;
+        while reduced > dec!(0.0625) {
+            reduced /= dec!(2);
+            halvings += 1;
+        }
+
+        let mut sum = Decimal::ONE;
+        let mut term = Decimal::ONE;
+        for i in 1..=16 {
+            term = term * reduced / Decimal::from(i);
+            if term.is_zero() {
+                break;
+            }
             sum += term;
         }
-        
+
+        for _ in 0..halvings {
+            sum = sum.checked_mul(sum).ok_or(MarketError::MathOverflow)?;
+        }
+
         Ok(sum)
     }
 
+    /// ln x as k·ln 2 + ln m for m in [1, 2), with ln m = 2·atanh((m−1)/(m+1)).
     fn ln(&self, x: Decimal) -> Result<Decimal> {
-        if x <= dec!(0.0) {
+        if x <= Decimal::ZERO {
             return Err(MarketError::MathOverflow.into());
         }
 
-        let mut guess = dec!(1.0);
-        for _ in 0..10 {
-            guess = guess + dec!(2.0) * (x - self.exp(guess)?) / (x + self.exp(guess)?);
+        let mut mantissa = x;
+        let mut exponent = 0i64;
+        while mantissa >= dec!(2) {
+            mantissa /= dec!(2);
+            exponent += 1;
+        }
+        while mantissa < Decimal::ONE {
+            mantissa *= dec!(2);
+            exponent -= 1;
         }
-        
-        Ok(guess)
+
+        let z = (mantissa - Decimal::ONE) / (mantissa + Decimal::ONE);
+        let z_squared = z * z;
+        let mut power = z;
+        let mut sum = Decimal::ZERO;
+        for i in 0..32 {
+            let term = power / Decimal::from(2 * i + 1);
+            if term.is_zero() {
+                break;
+            }
+            sum += term;
+            power *= z_squared;
+        }
+
+        Ok(Decimal::from(exponent) * LN_2 + dec!(2) * sum)
     }
 }
 
+/// Shares bought with `input_amount` collateral, or collateral paid for
+/// selling `input_amount` shares, on the LMSR cost function, and the fee of
+/// `fee_bps`. The fee is always collateral: taken from the input of a buy and
+/// from the proceeds of a sell.
 pub fn calculate_lmsr_output(
+    b: u64,
+    fee_bps: u16,
     input_amount: u64,
     yes_shares: u64,
     no_shares: u64,
     is_buying: bool,
     is_yes: bool,
-) -> Result<u64> {
-    let calculator = LMSRCalculator::new(DEFAULT_B, yes_shares, no_shares);
-    
-    // Apply fees (1%)
-    let fees = Decimal::from(input_amount) * dec!(0.01);
-    let input_after_fees = Decimal::from(input_amount) - fees;
+) -> Result<(u64, u64)> {
+    let calculator = LMSRCalculator::new(b, yes_shares, no_shares);
 
     if is_buying {
+        let fee = calculate_fee(input_amount, fee_bps);
+        let shares = calculator
+            .shares_for_collateral(Decimal::from(input_amount - fee), is_yes)?
+            .floor()
+            .to_u64()
+            .ok_or(MarketError::MathOverflow)?;
+        Ok((shares, fee))
+    } else {
+        let proceeds = calculator.calculate_proceeds_from_sell(input_amount, is_yes)?;
+        let fee = calculate_fee(proceeds, fee_bps);
+        Ok((proceeds - fee, fee))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapQuote {
+    pub amount_out: u64,
+    /// In collateral base units.
+    pub fee: u64,
+    /// Collateral per outcome share, scaled by 10^PRECISION.
+    pub average_price: u64,
+    pub price_before: u64,
+    pub price_after: u64,
+    /// Absolute change of the traded outcome's price, scaled by 10^PRECISION.
+    pub price_impact: u64,
+}
+
+/// Full breakdown of a swap against vault balances `yes_shares`/`no_shares`.
+pub fn quote_lmsr_swap(
+    b: u64,
+    fee_bps: u16,
+    input_amount: u64,
+    yes_shares: u64,
+    no_shares: u64,
+    is_buying: bool,
+    is_yes: bool,
+) -> Result<SwapQuote> {
+    let (amount_out, fee) = calculate_lmsr_output(
+        b,
+        fee_bps,
+        input_amount,
+        yes_shares,
+        no_shares,
+        is_buying,
+        is_yes,
+    )?;
+
+    let price_before =
+        price_to_u64(LMSRCalculator::new(b, yes_shares, no_shares).calculate_price(is_yes)?)?;
+
+    // Buys take outcome tokens out of the vault, sells put them back in.
+    let traded_shares = if is_yes { yes_shares } else { no_shares };
+    let traded_shares_after = if is_buying {
+        traded_shares
+            .checked_sub(amount_out)
+            .ok_or(MarketError::NoLiquidityInPool)?
+    } else {
+        traded_shares
+            .checked_add(input_amount)
+            .ok_or(MarketError::MathOverflow)?
+    };
+    let (yes_shares_after, no_shares_after) = if is_yes {
+        (traded_shares_after, no_shares)
+    } else {
+        (yes_shares, traded_shares_after)
+    };
+
+    let price_after = price_to_u64(
+        LMSRCalculator::new(b, yes_shares_after, no_shares_after)
+            .calculate_price(is_yes)?,
+    )?;
+
+    let (collateral, shares) = if is_buying {
+        (input_amount, amount_out)
+    } else {
+        (amount_out, input_amount)
+    };
+    let average_price = if shares == 0 {
+        0
+    } else {
+        price_to_u64(Decimal::from(collateral) / Decimal::from(shares))?
+    };
+
+    Ok(SwapQuote {
+        amount_out,
+        fee,
+        average_price,
+        price_before,
+        price_after,
+        price_impact: price_after.abs_diff(price_before),
+    })
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwitchQuote {
+    /// Shares of the other outcome received.
+    pub amount_out: u64,
+    /// Collateral released by the sold shares, less the fee, and spent on
+    /// the bought ones.
+    pub collateral: u64,
+    /// In collateral base units.
+    pub fee: u64,
+    /// Price of the bought outcome, scaled by 10^PRECISION.
+    pub price_before: u64,
+    pub price_after: u64,
+}
+
+/// Trades `amount` shares of one outcome for the other as one transition:
+/// the sold shares release collateral on the cost function, which buys the
+/// other outcome from the post-sale state. The fee is taken once, from the
+/// sale proceeds.
+pub fn quote_switch(
+    b: u64,
+    fee_bps: u16,
+    amount: u64,
+    yes_shares: u64,
+    no_shares: u64,
+    from_yes: bool,
+) -> Result<SwitchQuote> {
+    let to_yes = !from_yes;
+    let calculator = LMSRCalculator::new(b, yes_shares, no_shares);
+    let price_before = price_to_u64(calculator.calculate_price(to_yes)?)?;
+
+    let proceeds = calculator.calculate_proceeds_from_sell(amount, from_yes)?;
+    let fee = calculate_fee(proceeds, fee_bps);
+    let collateral = proceeds - fee;
+
+    // The sold shares go into their vault before the other side is priced.
+    let (yes_mid, no_mid) = if from_yes {
+        (yes_shares.checked_add(amount), Some(no_shares))
+    } else {
+        (Some(yes_shares), no_shares.checked_add(amount))
+    };
+    let yes_mid = yes_mid.ok_or(MarketError::MathOverflow)?;
+    let no_mid = no_mid.ok_or(MarketError::MathOverflow)?;
+
+    let amount_out = LMSRCalculator::new(b, yes_mid, no_mid)
+        .shares_for_collateral(Decimal::from(collateral), to_yes)?
+        .floor()
+        .to_u64()
+        .ok_or(MarketError::MathOverflow)?;
+
+    let (yes_after, no_after) = if to_yes {
+        (yes_mid.checked_sub(amount_out), Some(no_mid))
+    } else {
+        (Some(yes_mid), no_mid.checked_sub(amount_out))
+    };
+    let price_after = price_to_u64(
+        LMSRCalculator::new(
+            b,
+            yes_after.ok_or(MarketError::NoLiquidityInPool)?,
+            no_after.ok_or(MarketError::NoLiquidityInPool)?,
+        )
+        .calculate_price(to_yes)?,
+    )?;
+
+    Ok(SwitchQuote {
+        amount_out,
+        collateral,
+        fee,
+        price_before,
+        price_after,
+    })
+}
+
+/// Side and input of the trade that moves the `is_yes` price to
+/// `target_price`: the closed-form share delta b·(logit(p′) − logit(p)). A buy
+/// pays the cost of exactly those shares plus the fee; a sell puts them into
+/// the vault.
+pub fn trade_to_price_input(
+    b: u64,
+    fee_bps: u16,
+    yes_shares: u64,
+    no_shares: u64,
+    target_price: u64,
+    is_yes: bool,
+) -> Result<(bool, u64)> {
+    let calculator = LMSRCalculator::new(b, yes_shares, no_shares);
+
+    let delta = calculator.shares_to_price(price_from_u64(target_price), is_yes)?;
+    let is_buying = delta.is_sign_positive();
+    let shares = delta
+        .abs()
+        .round_dp(0)
+        .to_u64()
+        .ok_or(MarketError::MathOverflow)?;
+
+    let amount_in = match is_buying {
+        true => (Decimal::from(calculator.calculate_cost_to_buy(shares, is_yes)?)
+            / (Decimal::ONE - fee_rate(fee_bps)))
+            .ceil()
+            .to_u64()
+            .ok_or(MarketError::MathOverflow)?,
+        false => shares,
+    };
+
+    Ok((is_buying, amount_in))
+}
+
+/// Worst average price a swap may fill at, scaled by 10^PRECISION: a ceiling
+/// for buys and a floor for sells.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LimitOrder {
+    pub price: u64,
+    /// Shrink the swap so its average price stays within `price` instead of
+    /// rejecting it.
+    pub partial_fill: bool,
+}
+
+impl LimitOrder {
+    pub fn within(&self, is_buying: bool, price: u64) -> bool {
+        match is_buying {
+            true => price <= self.price,
+            false => price >= self.price,
+        }
+    }
+
+    /// Largest part of `input_amount` whose average price, fee included,
+    /// stays within the limit. Short of the whole swap, it fills up to the
+    /// marginal price at which the next share would cost the limit after the
+    /// fee: limit·(1−f) for buys, reached by paying b·ln((1−p)/(1−p′)) plus
+    /// the fee, and limit/(1−f) for sells, reached by putting
+    /// b·(logit(p) − logit(p′)) shares into the vault. Zero if the price is
+    /// already past it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn max_fill(
+        &self,
+        b: u64,
+        fee_bps: u16,
+        input_amount: u64,
+        yes_shares: u64,
+        no_shares: u64,
+        is_buying: bool,
+        is_yes: bool,
+    ) -> Result<u64> {
+        let full = quote_lmsr_swap(
+            b,
+            fee_bps,
+            input_amount,
+            yes_shares,
+            no_shares,
+            is_buying,
+            is_yes,
+        )?;
+        if self.within(is_buying, full.average_price) {
+            return Ok(input_amount);
+        }
+
+        let calculator = LMSRCalculator::new(b, yes_shares, no_shares);
+        let after_fee = Decimal::ONE - fee_rate(fee_bps);
+        let marginal_limit = match is_buying {
+            true => price_from_u64(self.price) * after_fee,
+            false => price_from_u64(self.price) / after_fee,
+        };
+
         let price = calculator.calculate_price(is_yes)?;
-        Ok((input_after_fees / price).round_dp(0).to_u64().ok_or(MarketError::MathOverflow)?)
+        let reachable = match is_buying {
+            true => price < marginal_limit,
+            false => price > marginal_limit,
+        };
+        if !reachable || marginal_limit >= Decimal::ONE {
+            return Ok(0);
+        }
+
+        let input = if is_buying {
+            calculator.collateral_to_price(marginal_limit, is_yes)? / after_fee
+        } else {
+            -calculator.shares_to_price(marginal_limit, is_yes)?
+        };
+
+        Ok(input
+            .max(Decimal::ZERO)
+            .floor()
+            .to_u64()
+            .ok_or(MarketError::MathOverflow)?
+            .min(input_amount))
+    }
+}
+
+/// Fee of `fee_bps` basis points on `amount` of collateral, rounded up.
+pub fn calculate_fee(amount: u64, fee_bps: u16) -> u64 {
+    (amount as u128 * fee_bps as u128).div_ceil(BPS_DENOMINATOR as u128) as u64
+}
+
+pub fn fee_rate(fee_bps: u16) -> Decimal {
+    Decimal::from(fee_bps) / Decimal::from(BPS_DENOMINATOR)
+}
+
+/// Vault balances that open a market at YES price `initial_price`: both hold
+/// [`INITIAL_VAULT_DEPTH`]·b shares and the less likely outcome's vault
+/// another b·|ln(p/(1−p))|. Prices and the subsidy only depend on the
+/// difference, so the shared depth is free to mint.
+pub fn initial_shares(b: u64, initial_price: u64) -> Result<(u64, u64)> {
+    let yes_shares = LMSRCalculator::new(b, 0, 0)
+        .shares_at_price(price_from_u64(initial_price), true)?;
+    let offset = yes_shares
+        .abs()
+        .round_dp(0)
+        .to_u64()
+        .ok_or(MarketError::MathOverflow)?;
+    let depth = b
+        .checked_mul(INITIAL_VAULT_DEPTH)
+        .ok_or(MarketError::MathOverflow)?;
+    let deeper = depth.checked_add(offset).ok_or(MarketError::MathOverflow)?;
+
+    Ok(if yes_shares.is_sign_positive() {
+        (deeper, depth)
+    } else {
+        (depth, deeper)
+    })
+}
+
+/// LMSR `b` for a collateral with `decimals`. Outcome mints share the
+/// collateral's decimals and the cost function is homogeneous, so scaling `b`
+/// with the base unit gives every collateral the same prices and depth.
+pub fn liquidity_for_decimals(decimals: u8) -> Result<u64> {
+    require!(
+        decimals <= MAX_COLLATERAL_DECIMALS,
+        MarketError::UnsupportedCollateralDecimals
+    );
+
+    Ok(match decimals.checked_sub(DEFAULT_COLLATERAL_DECIMALS) {
+        Some(extra) => DEFAULT_B * 10u64.pow(extra as u32),
+        None => DEFAULT_B / 10u64.pow((DEFAULT_COLLATERAL_DECIMALS - decimals) as u32),
+    })
+}
+
+/// Collateral owed for `tokens` of the winning outcome once the market
+/// settles: one unit per token. Only tokens held outside the market's own
+/// vault are redeemable, and a `vault_collateral` that can't cover all of
+/// them fails every claim rather than paying the first claimers in full.
+pub fn claim_payout(
+    tokens: u64,
+    vault_collateral: u64,
+    supply: u64,
+    vault_tokens: u64,
+) -> Result<u64> {
+    let outstanding = supply
+        .checked_sub(vault_tokens)
+        .ok_or(MarketError::MathUnderflow)?;
+    require!(
+        tokens > 0 && tokens <= outstanding,
+        MarketError::InsufficientBalance
+    );
+    require!(
+        outstanding <= vault_collateral,
+        MarketError::InsufficientCollateral
+    );
+
+    Ok(tokens)
+}
+
+/// Wrapped SOL under either token program. Such collateral is wrapped from
+/// and unwrapped back to the user's lamports around each trade or claim.
+pub fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == anchor_spl::token::spl_token::native_mint::ID
+        || *mint == anchor_spl::token_interface::spl_token_2022::native_mint::ID
+}
+
+/// Inverse of [`price_to_u64`].
+pub fn price_from_u64(price: u64) -> Decimal {
+    Decimal::from(price) / Decimal::from(10u64.pow(PRECISION))
+}
+
+/// Scales a probability in `[0, 1]` to an integer with `PRECISION` decimals.
+pub fn price_to_u64(price: Decimal) -> Result<u64> {
+    Ok((price * Decimal::from(10u64.pow(PRECISION)))
+        .round_dp(0)
+        .to_u64()
+        .ok_or(MarketError::MathOverflow)?)
+}
+
+/// Average price between two observations of the same market.
+pub fn calculate_twap(start: &Observation, end: &Observation) -> Result<u64> {
+    let elapsed = end
+        .timestamp
+        .checked_sub(start.timestamp)
+        .filter(|elapsed| *elapsed > 0)
+        .ok_or(MarketError::InvalidTwapWindow)?;
+
+    let cumulative_delta = end
+        .cumulative_price
+        .checked_sub(start.cumulative_price)
+        .ok_or(MarketError::MathUnderflow)?;
+
+    Ok((cumulative_delta / elapsed as u128) as u64)
+}
+
+/// Leaf committed to by a market's `access_root` for `user`.
+pub fn access_leaf(user: &Pubkey) -> [u8; 32] {
+    hashv(&[user.as_ref()]).to_bytes()
+}
+
+/// Parent of two Merkle nodes; children are sorted so proofs carry no
+/// left/right flags.
+pub fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    if a <= b {
+        hashv(&[a, b]).to_bytes()
     } else {
-        calculator.calculate_cost_to_buy(input_after_fees.to_u64().unwrap(), is_yes)
+        hashv(&[b, a]).to_bytes()
+    }
+}
+
+pub fn verify_merkle_proof(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(leaf, |node, sibling| hash_pair(&node, sibling));
+    computed == *root
+}
+
+/// Outcome token name after settlement, e.g. "VIRAT_YES WON", trimmed to fit
+/// the Metaplex name limit.
+pub fn settled_token_name(name: &str, won: bool) -> String {
+    let suffix = if won { " WON" } else { " LOST" };
+    let mut name = name.trim_end_matches('\0').to_string();
+    while name.len() + suffix.len() > MAX_TOKEN_NAME_LEN {
+        name.pop();
+    }
+    name + suffix
+}
+
+#[cfg(feature = "metaplex")]
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    use anchor_spl::metadata::ID as METADATA_ID;
+
+    Pubkey::find_program_address(
+        &[b"metadata", METADATA_ID.as_ref(), mint.as_ref()],
+        &METADATA_ID,
+    )
+    .0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::states::{PriceHistory, OBSERVATION_CAPACITY};
+
+    const B: u64 = DEFAULT_B;
+    const FEE_BPS: u16 = 100;
+    const VAULT: u64 = 5_000_000_000;
+
+    fn price(yes_shares: u64, no_shares: u64, is_yes: bool) -> u64 {
+        price_to_u64(
+            LMSRCalculator::new(B, yes_shares, no_shares)
+                .calculate_price(is_yes)
+                .unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn quote(input_amount: u64, is_buying: bool) -> SwapQuote {
+        quote_lmsr_swap(B, FEE_BPS, input_amount, VAULT, VAULT, is_buying, true).unwrap()
+    }
+
+    fn history(observation_interval: u32) -> PriceHistory {
+        PriceHistory {
+            market: Pubkey::default(),
+            index: 0,
+            count: 0,
+            observation_interval,
+            current: Observation::default(),
+            observations: [Observation::default(); OBSERVATION_CAPACITY],
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn cost_to_buy_inverts_shares_for_collateral() {
+        let calculator = LMSRCalculator::new(B, VAULT, VAULT);
+        let cost = calculator.calculate_cost_to_buy(100_000_000, true).unwrap();
+        let shares = calculator
+            .shares_for_collateral(Decimal::from(cost), true)
+            .unwrap();
+
+        assert_eq!(shares.floor(), dec!(100_000_000));
+        // Round trip through the vault gives back the cost, less rounding.
+        let proceeds = LMSRCalculator::new(B, VAULT - 100_000_000, VAULT)
+            .calculate_proceeds_from_sell(100_000_000, true)
+            .unwrap();
+        assert!(cost - proceeds <= 1);
+    }
+
+    #[test]
+    fn buying_raises_the_price() {
+        let quote = quote(100_000_000, true);
+
+        assert_eq!(quote.price_before, 500_000);
+        assert!(quote.price_after > quote.price_before);
+        assert!(quote.average_price > quote.price_before);
+        assert_eq!(
+            quote.price_after,
+            price(VAULT - quote.amount_out, VAULT, true)
+        );
+    }
+
+    #[test]
+    fn fees_are_charged_in_collateral() {
+        let buy = quote(100_000_000, true);
+        assert_eq!(buy.fee, 1_000_000);
+
+        let proceeds = LMSRCalculator::new(B, VAULT, VAULT)
+            .calculate_proceeds_from_sell(100_000_000, true)
+            .unwrap();
+        let sell = quote(100_000_000, false);
+        assert_eq!(sell.fee, calculate_fee(proceeds, FEE_BPS));
+        assert_eq!(sell.amount_out, proceeds - sell.fee);
+
+        assert_eq!(calculate_fee(1, FEE_BPS), 1);
+        assert_eq!(calculate_fee(1_000, 0), 0);
+    }
+
+    #[test]
+    fn shares_to_price_reaches_the_target() {
+        let calculator = LMSRCalculator::new(B, VAULT, VAULT);
+
+        for target in [dec!(0.2), dec!(0.5), dec!(0.73)] {
+            let delta = calculator.shares_to_price(target, true).unwrap();
+            let yes_after = (Decimal::from(VAULT) - delta).round().to_u64().unwrap();
+
+            assert!(price(yes_after, VAULT, true).abs_diff(price_to_u64(target).unwrap()) <= 1);
+        }
+        assert!(calculator.shares_to_price(Decimal::ONE, true).is_err());
+    }
+
+    #[test]
+    fn trade_to_price_input_lands_on_the_target() {
+        // (target, is_yes, expected side)
+        let cases = [(730_000, true, true), (200_000, true, false), (650_000, false, true)];
+        for (target, is_yes, buys) in cases {
+            let (is_buying, amount_in) =
+                trade_to_price_input(B, FEE_BPS, VAULT, VAULT, target, is_yes).unwrap();
+            let quote =
+                quote_lmsr_swap(B, FEE_BPS, amount_in, VAULT, VAULT, is_buying, is_yes).unwrap();
+
+            assert_eq!(is_buying, buys);
+            assert!(quote.price_after.abs_diff(target) <= TARGET_PRICE_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn max_fill_keeps_the_average_price_within_the_limit() {
+        let buy = LimitOrder {
+            price: 600_000,
+            partial_fill: true,
+        };
+        let filled = buy
+            .max_fill(B, FEE_BPS, 1_000_000_000, VAULT, VAULT, true, true)
+            .unwrap();
+        let fill = quote(filled, true);
+        assert!(filled < 1_000_000_000);
+        assert!(buy.within(true, fill.average_price));
+        // The last share costs the limit once the fee is added.
+        assert!(fill.price_after.abs_diff(594_000) <= 1);
+
+        let sell = LimitOrder {
+            price: 400_000,
+            partial_fill: true,
+        };
+        let filled = sell
+            .max_fill(B, FEE_BPS, 2_000_000_000, VAULT, VAULT, false, true)
+            .unwrap();
+        let fill = quote(filled, false);
+        assert!(filled < 2_000_000_000);
+        assert!(sell.within(false, fill.average_price));
+        assert!(fill.price_after.abs_diff(404_040) <= 1);
+
+        // Small enough to stay within the limit, or already past it.
+        assert_eq!(
+            buy.max_fill(B, FEE_BPS, 1_000_000, VAULT, VAULT, true, true)
+                .unwrap(),
+            1_000_000
+        );
+        let past = LimitOrder {
+            price: 500_000,
+            partial_fill: true,
+        };
+        assert_eq!(
+            past.max_fill(B, FEE_BPS, 1_000_000, VAULT, VAULT, true, true)
+                .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn quote_switch_moves_both_prices() {
+        let quote = quote_switch(B, FEE_BPS, 100_000_000, VAULT, VAULT, true).unwrap();
+        let proceeds = LMSRCalculator::new(B, VAULT, VAULT)
+            .calculate_proceeds_from_sell(100_000_000, true)
+            .unwrap();
+
+        assert_eq!(quote.fee, calculate_fee(proceeds, FEE_BPS));
+        assert_eq!(quote.collateral, proceeds - quote.fee);
+        assert!(quote.amount_out > 0);
+        assert!(quote.price_after > quote.price_before);
+        assert_eq!(
+            quote.price_after,
+            price(VAULT + 100_000_000, VAULT - quote.amount_out, false)
+        );
+        // Buying NO with the same collateral from the post-sale state.
+        let bought = LMSRCalculator::new(B, VAULT + 100_000_000, VAULT)
+            .calculate_cost_to_buy(quote.amount_out, false)
+            .unwrap();
+        assert!(bought <= quote.collateral);
+    }
+
+    #[test]
+    fn twap_between_observations() {
+        let start = Observation {
+            timestamp: 100,
+            slot: 1,
+            price: 400_000,
+            cumulative_price: 1_000,
+        };
+        let end = Observation {
+            timestamp: 110,
+            slot: 2,
+            price: 600_000,
+            cumulative_price: 1_000 + 500_000 * 10,
+        };
+
+        assert_eq!(calculate_twap(&start, &end).unwrap(), 500_000);
+        assert!(calculate_twap(&end, &start).is_err());
+        assert!(calculate_twap(&start, &start).is_err());
+    }
+
+    #[test]
+    fn price_history_spaces_observations() {
+        let mut history = history(10);
+        history.record(0, 0, 500_000).unwrap();
+        for timestamp in 1..10 {
+            history
+                .record(timestamp, timestamp as u64, 600_000)
+                .unwrap();
+        }
+
+        assert_eq!(history.count, 1);
+        assert_eq!(history.current.timestamp, 9);
+        assert_eq!(history.current.cumulative_price, 500_000 + 600_000 * 8);
+
+        history.record(10, 10, 700_000).unwrap();
+        assert_eq!(history.count, 2);
+        assert_eq!(history.index, 1);
+        assert_eq!(history.observations[1].timestamp, 10);
+        assert_eq!(history.observations[1].price, 700_000);
+    }
+
+    #[test]
+    fn price_history_ring_wraps() {
+        let mut history = history(1);
+        let total = OBSERVATION_CAPACITY as i64 + 8;
+        for timestamp in 0..total {
+            history
+                .record(timestamp, timestamp as u64, 500_000)
+                .unwrap();
+        }
+
+        assert_eq!(history.count as usize, OBSERVATION_CAPACITY);
+        assert_eq!(
+            history.index as usize,
+            (total as usize - 1) % OBSERVATION_CAPACITY
+        );
+        assert_eq!(history.latest().unwrap().timestamp, total - 1);
+
+        let now = total + 5;
+        assert_eq!(history.twap(20, now).unwrap(), 500_000);
+        // Older than the oldest entry still in the ring.
+        assert!(history.twap(now, now).is_err());
+        assert!(history.twap(0, now).is_err());
+    }
+
+    #[test]
+    fn twap_interpolates_the_window_start() {
+        let mut history = history(10);
+        history.record(0, 0, 400_000).unwrap();
+        history.record(10, 1, 600_000).unwrap();
+        // Not stored: inside the interval, but carried by `current`.
+        history.record(15, 2, 800_000).unwrap();
+
+        // 400k over 0..10, 600k over 10..15 and 800k over 15..20.
+        assert_eq!(history.twap(20, 20).unwrap(), 550_000);
+        assert_eq!(history.twap(10, 20).unwrap(), 700_000);
+        // Past the last update the price has not moved.
+        assert_eq!(history.twap(2, 20).unwrap(), 800_000);
+    }
+
+    #[test]
+    fn merkle_proof_verifies_members_only() {
+        let users: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let leaves: Vec<[u8; 32]> = users.iter().map(access_leaf).collect();
+        let left = hash_pair(&leaves[0], &leaves[1]);
+        let right = hash_pair(&leaves[2], &leaves[3]);
+        let root = hash_pair(&left, &right);
+
+        assert!(verify_merkle_proof(&[leaves[3], left], &root, leaves[2]));
+        assert!(verify_merkle_proof(&[leaves[0], right], &root, leaves[1]));
+        assert!(!verify_merkle_proof(&[leaves[3], left], &root, leaves[1]));
+        assert!(!verify_merkle_proof(
+            &[leaves[3], left],
+            &root,
+            access_leaf(&Pubkey::new_unique())
+        ));
+        assert!(verify_merkle_proof(&[], &leaves[0], leaves[0]));
+    }
+
+    #[test]
+    fn initial_shares_round_trip() {
+        let depth = B * INITIAL_VAULT_DEPTH;
+        for initial_price in [10_000, 250_000, 500_000, 700_000, 990_000] {
+            let (yes_shares, no_shares) = initial_shares(B, initial_price).unwrap();
+
+            assert_eq!(yes_shares.min(no_shares), depth);
+            assert!(price(yes_shares, no_shares, true).abs_diff(initial_price) <= 1);
+        }
+        // Both vaults can be bought from at an even open.
+        assert_eq!(initial_shares(B, 500_000).unwrap(), (depth, depth));
+        let first_buy = quote_lmsr_swap(B, FEE_BPS, 1_000_000, depth, depth, true, true);
+        assert!(first_buy.unwrap().amount_out > 0);
+        assert!(initial_shares(B, 0).is_err());
+    }
+
+    #[test]
+    fn liquidity_scales_with_decimals() {
+        assert_eq!(liquidity_for_decimals(6).unwrap(), DEFAULT_B);
+        assert_eq!(liquidity_for_decimals(9).unwrap(), DEFAULT_B * 1_000);
+        assert_eq!(liquidity_for_decimals(0).unwrap(), DEFAULT_B / 1_000_000);
+        assert!(liquidity_for_decimals(MAX_COLLATERAL_DECIMALS + 1).is_err());
+
+        // The same trade in whole tokens prices the same at any decimals.
+        let quote_at = |decimals: u8| {
+            let unit = 10u64.pow(decimals as u32);
+            let b = liquidity_for_decimals(decimals).unwrap();
+            quote_lmsr_swap(b, FEE_BPS, 100 * unit, 5_000 * unit, 5_000 * unit, true, true)
+                .unwrap()
+                .price_after
+        };
+        assert_eq!(quote_at(6), quote_at(9));
+    }
+
+    #[test]
+    fn claim_payout_pays_one_unit_per_token() {
+        // 1_000 tokens minted, 400 still in the vault, 700 collateral.
+        assert_eq!(claim_payout(250, 700, 1_000, 400).unwrap(), 250);
+        assert_eq!(claim_payout(600, 700, 1_000, 400).unwrap(), 600);
+        assert_eq!(
+            claim_payout(601, 700, 1_000, 400).unwrap_err(),
+            MarketError::InsufficientBalance.into()
+        );
+        // An insolvent vault fails every claim, not just the last one.
+        assert_eq!(
+            claim_payout(1, 599, 1_000, 400).unwrap_err(),
+            MarketError::InsufficientCollateral.into()
+        );
     }
 }
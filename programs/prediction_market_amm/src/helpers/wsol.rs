@@ -0,0 +1,65 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+use anchor_spl::token_interface::{close_account, sync_native, CloseAccount, SyncNative};
+
+use crate::{error::MarketError, helpers::is_native_mint};
+
+/// Seed of the temporary account wrapped SOL proceeds pass through on their
+/// way back to the user's lamports: `[UNWRAP_SEED, user]`.
+pub const UNWRAP_SEED: &[u8] = b"unwrap";
+
+/// Wraps `amount` of `from`'s lamports straight into the market's wrapped SOL
+/// `vault`, so buyers need no wrapped SOL account of their own.
+pub fn wrap_sol<'info>(
+    from: AccountInfo<'info>,
+    vault: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let accounts = Transfer {
+        from,
+        to: vault.clone(),
+    };
+    transfer(CpiContext::new(system_program, accounts), amount)?;
+
+    let accounts = SyncNative { account: vault };
+    sync_native(CpiContext::new(token_program, accounts))
+}
+
+/// Where collateral paid out of a vault goes: the temporary unwrap account on
+/// wrapped SOL markets when one was passed, else the owner's collateral
+/// account.
+pub fn payout_account<'info>(
+    mint: &Pubkey,
+    unwrap_account: Option<AccountInfo<'info>>,
+    collateral_account: Option<AccountInfo<'info>>,
+) -> Result<AccountInfo<'info>> {
+    match (unwrap_account, collateral_account) {
+        (Some(account), _) => {
+            require!(is_native_mint(mint), MarketError::InvalidToken);
+            Ok(account)
+        }
+        (None, Some(account)) => Ok(account),
+        (None, None) => err!(MarketError::MissingCollateralAccount),
+    }
+}
+
+/// Closes the temporary unwrap account into `owner`, paying out as lamports
+/// exactly what this instruction moved into it plus its rent. The owner's
+/// own wrapped SOL account is never touched.
+pub fn unwrap_sol<'info>(
+    unwrap_account: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+) -> Result<()> {
+    let accounts = CloseAccount {
+        account: unwrap_account,
+        destination: owner.clone(),
+        authority: owner,
+    };
+
+    close_account(CpiContext::new(token_program, accounts))
+}
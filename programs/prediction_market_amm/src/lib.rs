@@ -1,11 +1,16 @@
 use anchor_lang::prelude::*;
 
 mod contexts;
-mod error;
-mod helpers;
-mod states;
+pub mod error;
+pub mod events;
+pub mod helpers;
+pub mod oracle;
+pub mod states;
 
 use contexts::*;
+use helpers::{LimitOrder, SwapQuote};
+use oracle::PriceData;
+use states::{CircuitBreaker, MarketInfoArgs, MarketLimits, OutcomeMetadata};
 
 declare_id!("3a88faQsXFty3G1Tnsq17gZydUnkQ9WBnAXNVWAZ5YLL");
 
@@ -14,6 +19,7 @@ pub mod prediction_market_amm {
 
     use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     #[inline(never)]
     pub fn initialize(
         ctx: Context<Initialize>,
@@ -27,8 +33,15 @@ pub mod prediction_market_amm {
         token_no_uri: String,
         fee: u16,
         end_time: i64,
+        keeper_tip_bps: u16,
+        limits: MarketLimits,
+        circuit_breaker: CircuitBreaker,
+        initial_price: u64,
+        outcome_metadata: OutcomeMetadata,
+        observation_interval: u32,
+        claim_window: u32,
     ) -> Result<()> {
-        ctx.accounts.save_market(
+        let event = ctx.accounts.save_market(
             seed,
             name,
             token_yes_name,
@@ -39,8 +52,17 @@ pub mod prediction_market_amm {
             token_no_uri,
             fee,
             end_time,
+            keeper_tip_bps,
+            limits,
+            circuit_breaker,
+            initial_price,
+            outcome_metadata,
+            observation_interval,
+            claim_window,
             &ctx.bumps,
-        )
+        )?;
+        emit_cpi!(event);
+        Ok(())
     }
 
     pub fn add_liquidity(
@@ -48,10 +70,24 @@ pub mod prediction_market_amm {
         max_yes: u64,
         max_no: u64,
         expiration: i64,
+        proof: Vec<[u8; 32]>,
     ) -> Result<()> {
-        ctx.accounts.deposit(max_no, max_yes, expiration)
+        let event = ctx
+            .accounts
+            .deposit(max_no, max_yes, expiration, &proof)?;
+        emit_cpi!(event);
+        Ok(())
     }
 
+    pub fn open_position(ctx: Context<OpenPosition>) -> Result<()> {
+        ctx.accounts.open_position(&ctx.bumps)
+    }
+
+    pub fn close_position_account(ctx: Context<ClosePositionAccount>) -> Result<()> {
+        ctx.accounts.close_position_account()
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn swap(
         ctx: Context<Swap>,
         is_usdc_to_token: bool,
@@ -59,24 +95,177 @@ pub mod prediction_market_amm {
         is_yes: bool,
         min_out: u64,
         expiration: i64,
+        proof: Vec<[u8; 32]>,
+        limit: Option<LimitOrder>,
+    ) -> Result<()> {
+        let outcome = ctx.accounts.swap(
+            is_usdc_to_token,
+            amount,
+            is_yes,
+            min_out,
+            expiration,
+            &proof,
+            limit,
+        )?;
+        match outcome {
+            SwapOutcome::Filled(event) => emit_cpi!(event),
+            SwapOutcome::Switched(event) => emit_cpi!(event),
+            SwapOutcome::Merged(event) => emit_cpi!(event),
+        }
+        Ok(())
+    }
+
+    pub fn trade_to_price(
+        ctx: Context<Swap>,
+        target_price: u64,
+        is_yes: bool,
+        max_spend: u64,
+    ) -> Result<()> {
+        let outcome = ctx
+            .accounts
+            .trade_to_price(target_price, is_yes, max_spend)?;
+        match outcome {
+            SwapOutcome::Filled(event) => emit_cpi!(event),
+            SwapOutcome::Switched(event) => emit_cpi!(event),
+            SwapOutcome::Merged(event) => emit_cpi!(event),
+        }
+        Ok(())
+    }
+
+    pub fn switch_side(
+        ctx: Context<Swap>,
+        amount: u64,
+        from_yes: bool,
+        min_out: u64,
+    ) -> Result<()> {
+        let outcome = ctx
+            .accounts
+            .switch_side(amount, from_yes, min_out)?;
+        match outcome {
+            SwapOutcome::Filled(event) => emit_cpi!(event),
+            SwapOutcome::Switched(event) => emit_cpi!(event),
+            SwapOutcome::Merged(event) => emit_cpi!(event),
+        }
+        Ok(())
+    }
+
+    pub fn close_position(
+        ctx: Context<Swap>,
+        is_yes: bool,
+        min_out: u64,
+        close_ata: bool,
+    ) -> Result<()> {
+        let outcomes = ctx
+            .accounts
+            .close_position(is_yes, min_out, close_ata)?;
+        for outcome in outcomes {
+            match outcome {
+                SwapOutcome::Filled(event) => emit_cpi!(event),
+                    SwapOutcome::Switched(event) => emit_cpi!(event),
+                SwapOutcome::Merged(event) => emit_cpi!(event),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn quote_swap(
+        ctx: Context<QuoteSwap>,
+        is_usdc_to_token: bool,
+        amount: u64,
+        is_yes: bool,
+    ) -> Result<SwapQuote> {
+        ctx.accounts.quote_swap(is_usdc_to_token, amount, is_yes)
+    }
+
+    pub fn settle(
+        ctx: Context<SettleMarket>,
+        is_resolved: bool,
+        winner: Option<bool>,
+        mark_outcomes: bool,
     ) -> Result<()> {
-        ctx.accounts
-            .swap(is_usdc_to_token, amount, is_yes, min_out, expiration)
+        let event = ctx.accounts.settle(is_resolved, winner, mark_outcomes)?;
+        emit_cpi!(event);
+        Ok(())
     }
 
-    pub fn settle(ctx: Context<SettleMarket>, is_resolved: bool) -> Result<()> {
-        ctx.accounts.settle(is_resolved)
+    pub fn freeze_losing_account(ctx: Context<FreezeLosingAccount>) -> Result<()> {
+        let event = ctx.accounts.freeze_losing_account()?;
+        emit_cpi!(event);
+        Ok(())
     }
 
     pub fn claim(ctx: Context<ClaimReward>, is_yes: bool) -> Result<()> {
-        ctx.accounts.claim(is_yes)
+        let event = ctx.accounts.claim(is_yes)?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn claim_many<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimMany<'info>>) -> Result<()> {
+        let events = ctx.accounts.claim_many(ctx.remaining_accounts)?;
+        for event in events {
+            emit_cpi!(event);
+        }
+        Ok(())
+    }
+
+    pub fn approve_crank(ctx: Context<ApproveCrank>, amount: u64) -> Result<()> {
+        let event = ctx.accounts.approve_crank(amount)?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn crank_claim(ctx: Context<CrankClaim>, is_yes: bool) -> Result<()> {
+        let event = ctx.accounts.crank_claim(is_yes)?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn close_market(ctx: Context<CloseMarket>) -> Result<()> {
+        let event = ctx.accounts.close_market()?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn get_price(ctx: Context<GetPrice>, twap_window: i64) -> Result<PriceData> {
+        ctx.accounts.get_price(twap_window)
     }
 
     pub fn lock(ctx: Context<Update>) -> Result<()> {
-        ctx.accounts.lock()
+        let event = ctx.accounts.lock()?;
+        emit_cpi!(event);
+        Ok(())
     }
 
     pub fn unlock(ctx: Context<Update>) -> Result<()> {
-        ctx.accounts.unlock()
+        let event = ctx.accounts.unlock()?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn set_access_root(
+        ctx: Context<SetAccessRoot>,
+        access_root: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let event = ctx.accounts.set_access_root(access_root)?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn grant_access(ctx: Context<GrantAccess>, user: Pubkey) -> Result<()> {
+        let event = ctx.accounts.grant_access(user, &ctx.bumps)?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn revoke_access(ctx: Context<RevokeAccess>, user: Pubkey) -> Result<()> {
+        let event = ctx.accounts.revoke_access(user)?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn set_market_info(ctx: Context<SetMarketInfo>, info: MarketInfoArgs) -> Result<()> {
+        let event = ctx.accounts.set_market_info(info, &ctx.bumps)?;
+        emit_cpi!(event);
+        Ok(())
     }
 }
@@ -0,0 +1,43 @@
+use anchor_lang::{prelude::*, solana_program::program::get_return_data};
+
+use crate::error::MarketError;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarketStatus {
+    Open,
+    Locked,
+    Expired,
+    Settled,
+}
+
+/// Return data of `get_price`. Prices are YES probabilities scaled by 10^PRECISION.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct PriceData {
+    pub market: Pubkey,
+    pub spot_price: u64,
+    /// `None` when the window is not positive or reaches past the recorded
+    /// history.
+    pub twap: Option<u64>,
+    pub status: MarketStatus,
+    /// `Some(true)` if YES won, once the market is settled with a winner.
+    pub winner: Option<bool>,
+    pub last_update_slot: u64,
+}
+
+/// Decodes the return data left by this program's `get_price`.
+pub fn read_price_data() -> Result<PriceData> {
+    let (program_id, data) = get_return_data().ok_or(MarketError::InvalidReturnData)?;
+    require_keys_eq!(program_id, crate::ID, MarketError::InvalidReturnData);
+
+    PriceData::try_from_slice(&data).map_err(|_| MarketError::InvalidReturnData.into())
+}
+
+/// Reads a market's price from another program.
+#[cfg(feature = "cpi")]
+pub fn get_price<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, crate::cpi::accounts::GetPrice<'info>>,
+    twap_window: i64,
+) -> Result<PriceData> {
+    crate::cpi::get_price(ctx, twap_window)?;
+    read_price_data()
+}
@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+/// Grants `user` access to a gated market, issued by the market authority.
+#[account]
+#[derive(InitSpace)]
+pub struct AllowlistEntry {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    /// Paid the rent and may revoke the entry, even once the market closed.
+    pub authority: Pubkey,
+    pub bump: u8,
+}
@@ -1,5 +1,10 @@
 use anchor_lang::prelude::*;
 
+use crate::{
+    error::MarketError,
+    helpers::{access_leaf, liquidity_for_decimals, verify_merkle_proof},
+};
+
 #[account]
 #[derive(InitSpace)]
 pub struct Market {
@@ -8,10 +13,166 @@ pub struct Market {
     pub seed: u64,
     pub mint_yes: Pubkey,
     pub mint_no: Pubkey,
+    /// Collateral every trade, deposit and claim settles in.
+    pub mint_usdc: Pubkey,
+    /// Collateral paid in by `add_liquidity`, one unit per YES+NO pair.
     pub total_liquidity: u64,
     pub fee: u16,
     pub locked: bool,
     pub end_time: i64,
     pub settled: bool,
+    pub keeper_tip_bps: u16,
+    pub payer: Pubkey,
+    pub treasury: Pubkey,
+    pub settled_at: i64,
+    /// Seconds after `settled_at` that winners have to claim before
+    /// `close_market` may sweep their collateral; 0 waits for every claim.
+    pub claim_window: u32,
+    pub limits: MarketLimits,
+    pub authority: Pubkey,
+    /// Merkle root of wallets allowed to trade; `None` leaves the market open.
+    pub access_root: Option<[u8; 32]>,
+    pub circuit_breaker: CircuitBreaker,
+    /// YES price the breaker measures moves against, and the slot and time
+    /// it was taken.
+    pub reference_price: u64,
+    pub reference_slot: u64,
+    pub reference_timestamp: i64,
+    /// Opening YES price, scaled by 10^PRECISION.
+    pub initial_price: u64,
+    /// Collateral the creator paid into `vault_usdc` at `initialize`: the
+    /// market maker's worst-case loss from the opening prices.
+    pub subsidy: u64,
+    pub outcome_metadata: OutcomeMetadata,
+    /// `Some(true)` if YES won, when `settle` was told the outcome.
+    pub winner: Option<bool>,
+    /// Decimals of the collateral mint, shared by the outcome mints.
+    pub collateral_decimals: u8,
+    /// Set by the first swap or deposit and never cleared; `MarketInfo` is
+    /// fixed from then on.
+    pub trading_opened: bool,
     pub market_bump: u8
+}
+
+impl Market {
+    /// LMSR `b` in base units of this market's collateral.
+    pub fn liquidity(&self) -> Result<u64> {
+        liquidity_for_decimals(self.collateral_decimals)
+    }
+
+    /// Trades and deposits stop at `end_time` and once the market settles;
+    /// claiming is the only way out after that.
+    pub fn check_trading_open(&self, now: i64) -> Result<()> {
+        require!(!self.settled, MarketError::MarketSettled);
+        require!(now <= self.end_time, MarketError::MarketEnded);
+        Ok(())
+    }
+
+    /// Gated markets admit `user` if they hold an allowlist entry or prove
+    /// membership in `access_root`.
+    pub fn check_access(
+        &self,
+        user: &Pubkey,
+        allowlisted: bool,
+        proof: &[[u8; 32]],
+    ) -> Result<()> {
+        let Some(root) = &self.access_root else {
+            return Ok(());
+        };
+
+        require!(
+            allowlisted || verify_merkle_proof(proof, root, access_leaf(user)),
+            MarketError::AccessDenied
+        );
+        Ok(())
+    }
+
+    /// Whether `window_slots` have passed since the breaker's reference was
+    /// taken, so it should move on.
+    pub fn breaker_window_elapsed(&self, slot: u64) -> bool {
+        slot >= self
+            .reference_slot
+            .saturating_add(self.circuit_breaker.window_slots)
+    }
+
+    pub fn roll_reference(&mut self, price: u64, slot: u64, timestamp: i64) {
+        self.reference_price = price;
+        self.reference_slot = slot;
+        self.reference_timestamp = timestamp;
+    }
+
+    /// Rejects moving the YES price from `price_before` to `price_after` if
+    /// one step or the move from the reference breaks the circuit breaker.
+    pub fn check_circuit_breaker(&self, price_before: u64, price_after: u64) -> Result<()> {
+        let breaker = self.circuit_breaker;
+
+        let impact_ok = breaker.max_price_impact == 0
+            || price_after.abs_diff(price_before) <= breaker.max_price_impact;
+        let move_ok = breaker.max_price_move == 0
+            || price_after.abs_diff(self.reference_price) <= breaker.max_price_move;
+
+        require!(impact_ok && move_ok, MarketError::CircuitBreakerTripped);
+        Ok(())
+    }
+}
+
+/// Where the YES/NO mints keep their name, symbol and URI.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub enum OutcomeMetadata {
+    /// A Metaplex metadata account; needs the `metaplex` feature.
+    #[default]
+    Metaplex,
+    /// Token-2022 metadata-pointer and token-metadata extensions on the mint
+    /// itself; needs the Token-2022 program.
+    TokenExtensions,
+}
+
+/// Rejects trades that move the YES price too fast. Prices are scaled by
+/// 10^PRECISION and zero disables a check.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, InitSpace)]
+pub struct CircuitBreaker {
+    /// Largest move from the reference price within `window_slots`.
+    pub max_price_move: u64,
+    pub window_slots: u64,
+    /// Largest move a single swap may cause.
+    pub max_price_impact: u64,
+}
+
+/// Responsible-gaming caps, in USDC base units. Zero disables a cap.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, InitSpace)]
+pub struct MarketLimits {
+    /// Largest amount a single swap or deposit may put in.
+    pub max_trade_size: u64,
+    /// Largest net USDC a user may have at risk in this market.
+    pub max_user_exposure: u64,
+    /// Largest number of outcome tokens held outside the vaults.
+    pub max_open_interest: u64,
+}
+
+impl MarketLimits {
+    pub fn check_trade_size(&self, amount: u64) -> Result<()> {
+        require!(
+            self.max_trade_size == 0 || amount <= self.max_trade_size,
+            MarketError::TradeSizeExceeded
+        );
+        Ok(())
+    }
+
+    pub fn check_user_exposure(&self, exposure: u64) -> Result<()> {
+        require!(
+            self.max_user_exposure == 0 || exposure <= self.max_user_exposure,
+            MarketError::ExposureLimitExceeded
+        );
+        Ok(())
+    }
+
+    pub fn check_open_interest(&self, open_interest: u64) -> Result<()> {
+        require!(
+            self.max_open_interest == 0 || open_interest <= self.max_open_interest,
+            MarketError::OpenInterestLimitExceeded
+        );
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+
+use crate::error::MarketError;
+
+pub const MAX_TITLE_LEN: usize = 128;
+pub const MAX_TAGS: usize = 4;
+pub const MAX_RESOLUTION_SOURCE_LEN: usize = 200;
+pub const MAX_EVENT_ID_LEN: usize = 64;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum MarketTag {
+    Sport,
+    League,
+    Crypto,
+    Politics,
+    Other,
+}
+
+/// Describes what a market resolves on, so clients need no off-chain
+/// database. Set by the market authority until trading opens.
+#[account]
+#[derive(InitSpace)]
+pub struct MarketInfo {
+    pub market: Pubkey,
+    #[max_len(MAX_TITLE_LEN)]
+    pub title: String,
+    pub category: MarketTag,
+    #[max_len(MAX_TAGS)]
+    pub tags: Vec<MarketTag>,
+    /// URL of the source the outcome is read from.
+    #[max_len(MAX_RESOLUTION_SOURCE_LEN)]
+    pub resolution_source: String,
+    /// SHA-256 of the full rules text.
+    pub rules_hash: [u8; 32],
+    /// Identifier of the underlying event at its source, e.g. a fixture ID.
+    #[max_len(MAX_EVENT_ID_LEN)]
+    pub event_id: String,
+    pub bump: u8,
+}
+
+/// Everything in [`MarketInfo`] the authority chooses.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MarketInfoArgs {
+    pub title: String,
+    pub category: MarketTag,
+    pub tags: Vec<MarketTag>,
+    pub resolution_source: String,
+    pub rules_hash: [u8; 32],
+    pub event_id: String,
+}
+
+impl MarketInfoArgs {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.title.len() <= MAX_TITLE_LEN
+                && self.tags.len() <= MAX_TAGS
+                && self.resolution_source.len() <= MAX_RESOLUTION_SOURCE_LEN
+                && self.event_id.len() <= MAX_EVENT_ID_LEN,
+            MarketError::MarketInfoTooLong
+        );
+        Ok(())
+    }
+}
@@ -1,2 +1,14 @@
 pub mod market;
-pub use market::*;
\ No newline at end of file
+pub use market::*;
+
+pub mod price_history;
+pub use price_history::*;
+
+pub mod position;
+pub use position::*;
+
+pub mod allowlist;
+pub use allowlist::*;
+
+pub mod market_info;
+pub use market_info::*;
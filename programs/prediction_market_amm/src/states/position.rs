@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+
+use crate::error::MarketError;
+
+/// A user's trading history in one market, opened with `open_position`.
+#[account]
+#[derive(InitSpace)]
+pub struct Position {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub yes_bought: u64,
+    pub yes_sold: u64,
+    pub no_bought: u64,
+    pub no_sold: u64,
+    pub usdc_spent: u64,
+    pub usdc_received: u64,
+    /// Collateral paid in fees on buys, sells and switches.
+    pub fees_paid: u64,
+    /// Collateral paid in by `add_liquidity`, one unit per YES+NO pair it
+    /// minted into the vaults.
+    pub liquidity_added: u64,
+    /// USDC paid out by any claim path after settlement.
+    pub payout: u64,
+    /// USDC received plus payout minus USDC spent, set once claimed.
+    pub realized_pnl: i64,
+    pub finalized: bool,
+    pub bump: u8,
+}
+
+impl Position {
+    pub fn record_trade(
+        &mut self,
+        is_buying: bool,
+        is_yes: bool,
+        amount_in: u64,
+        amount_out: u64,
+        fee: u64,
+    ) -> Result<()> {
+        let (usdc, shares, shares_delta) = match (is_buying, is_yes) {
+            (true, true) => (&mut self.usdc_spent, &mut self.yes_bought, amount_out),
+            (true, false) => (&mut self.usdc_spent, &mut self.no_bought, amount_out),
+            (false, true) => (&mut self.usdc_received, &mut self.yes_sold, amount_in),
+            (false, false) => (&mut self.usdc_received, &mut self.no_sold, amount_in),
+        };
+        let usdc_delta = if is_buying { amount_in } else { amount_out };
+
+        *usdc = usdc
+            .checked_add(usdc_delta)
+            .ok_or(MarketError::MathOverflow)?;
+        *shares = shares
+            .checked_add(shares_delta)
+            .ok_or(MarketError::MathOverflow)?;
+        self.fees_paid = self
+            .fees_paid
+            .checked_add(fee)
+            .ok_or(MarketError::MathOverflow)?;
+
+        Ok(())
+    }
+
+    pub fn record_deposit(&mut self, collateral: u64) -> Result<()> {
+        self.liquidity_added = self
+            .liquidity_added
+            .checked_add(collateral)
+            .ok_or(MarketError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// USDC spent or added as liquidity that has not been taken back out by
+    /// selling.
+    pub fn net_exposure(&self) -> u64 {
+        self.usdc_spent
+            .saturating_add(self.liquidity_added)
+            .saturating_sub(self.usdc_received)
+    }
+
+    pub fn finalize(&mut self, payout: u64) -> Result<()> {
+        self.payout = self
+            .payout
+            .checked_add(payout)
+            .ok_or(MarketError::MathOverflow)?;
+
+        let proceeds = (self.usdc_received as i128)
+            .checked_add(self.payout as i128)
+            .and_then(|proceeds| proceeds.checked_sub(self.usdc_spent as i128))
+            .ok_or(MarketError::MathOverflow)?;
+
+        self.realized_pnl = i64::try_from(proceeds).map_err(|_| MarketError::MathOverflow)?;
+        self.finalized = true;
+
+        Ok(())
+    }
+}
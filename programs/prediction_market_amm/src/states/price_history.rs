@@ -0,0 +1,147 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::MarketError, helpers::calculate_twap};
+
+pub const OBSERVATION_CAPACITY: usize = 32;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct Observation {
+    pub timestamp: i64,
+    pub slot: u64,
+    /// YES spot price scaled by 10^PRECISION, in effect from `timestamp`.
+    pub price: u64,
+    /// Sum of price * seconds since the first observation.
+    pub cumulative_price: u128,
+}
+
+impl Observation {
+    /// Carries the accumulator forward to `timestamp` at the current price.
+    pub fn extrapolate(&self, timestamp: i64) -> Result<Observation> {
+        let elapsed = timestamp
+            .checked_sub(self.timestamp)
+            .filter(|elapsed| *elapsed >= 0)
+            .ok_or(MarketError::InvalidTwapWindow)?;
+
+        let cumulative_price = (self.price as u128)
+            .checked_mul(elapsed as u128)
+            .and_then(|delta| self.cumulative_price.checked_add(delta))
+            .ok_or(MarketError::MathOverflow)?;
+
+        Ok(Observation {
+            timestamp,
+            slot: self.slot,
+            price: self.price,
+            cumulative_price,
+        })
+    }
+}
+
+/// Time-weighted price history of a market. Every trade updates
+/// `current`; it is copied into the ring at most once per
+/// `observation_interval`, so the ring covers
+/// `OBSERVATION_CAPACITY * observation_interval` seconds however busy the
+/// market is.
+#[account]
+#[derive(InitSpace)]
+pub struct PriceHistory {
+    pub market: Pubkey,
+    pub index: u16,
+    pub count: u16,
+    /// Minimum seconds between ring entries, chosen at `initialize`.
+    pub observation_interval: u32,
+    /// Accumulator and price as of the last update.
+    pub current: Observation,
+    pub observations: [Observation; OBSERVATION_CAPACITY],
+    pub bump: u8,
+}
+
+impl PriceHistory {
+    pub fn latest(&self) -> Result<&Observation> {
+        require!(self.count > 0, MarketError::InsufficientPriceHistory);
+        Ok(&self.current)
+    }
+
+    pub fn record(&mut self, timestamp: i64, slot: u64, price: u64) -> Result<()> {
+        if self.count == 0 {
+            self.current = Observation {
+                timestamp,
+                slot,
+                price,
+                cumulative_price: 0,
+            };
+            self.observations[0] = self.current;
+            self.index = 0;
+            self.count = 1;
+            return Ok(());
+        }
+
+        // The old price held until `timestamp`; the new one applies from it.
+        self.current = self.current.extrapolate(timestamp)?;
+        self.current.slot = slot;
+        self.current.price = price;
+
+        let stored = &self.observations[self.index as usize];
+        if timestamp - stored.timestamp < self.observation_interval as i64 {
+            return Ok(());
+        }
+
+        self.index = ((self.index as usize + 1) % OBSERVATION_CAPACITY) as u16;
+        self.observations[self.index as usize] = self.current;
+        self.count = (self.count + 1).min(OBSERVATION_CAPACITY as u16);
+
+        Ok(())
+    }
+
+    /// Time-weighted YES price over the `window` seconds ending at `now`.
+    /// Between two ring entries the price may have changed without being
+    /// stored, so the accumulator at the window's start is interpolated
+    /// between the entries around it.
+    pub fn twap(&self, window: i64, now: i64) -> Result<u64> {
+        require!(window > 0, MarketError::InvalidTwapWindow);
+
+        let start_timestamp = now.checked_sub(window).ok_or(MarketError::MathUnderflow)?;
+        let end = self.latest()?.extrapolate(now)?;
+
+        if self.current.timestamp <= start_timestamp {
+            return calculate_twap(&self.current.extrapolate(start_timestamp)?, &end);
+        }
+
+        // Newest first, from the live accumulator back through the ring.
+        let points: Vec<&Observation> = std::iter::once(&self.current)
+            .chain((0..self.count as usize).map(|i| {
+                let index = (self.index as usize + OBSERVATION_CAPACITY - i) % OBSERVATION_CAPACITY;
+                &self.observations[index]
+            }))
+            .collect();
+
+        let (after, before) = points
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .find(|(_, before)| before.timestamp <= start_timestamp)
+            .ok_or(MarketError::InsufficientPriceHistory)?;
+
+        calculate_twap(&interpolate(before, after, start_timestamp)?, &end)
+    }
+}
+
+/// Accumulator at `timestamp`, between `before` and `after`, assuming their
+/// average price held throughout.
+fn interpolate(before: &Observation, after: &Observation, timestamp: i64) -> Result<Observation> {
+    let span = after.timestamp - before.timestamp;
+    if span <= 0 {
+        return before.extrapolate(timestamp);
+    }
+
+    let elapsed = (timestamp - before.timestamp) as u128;
+    let cumulative_delta = after
+        .cumulative_price
+        .checked_sub(before.cumulative_price)
+        .ok_or(MarketError::MathUnderflow)?;
+
+    Ok(Observation {
+        timestamp,
+        slot: before.slot,
+        price: before.price,
+        cumulative_price: before.cumulative_price + cumulative_delta * elapsed / span as u128,
+    })
+}
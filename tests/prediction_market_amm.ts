@@ -11,6 +11,7 @@ import {
   SendTransactionError,
 } from "@solana/web3.js";
 import NodeWallet from "@coral-xyz/anchor/dist/cjs/nodewallet";
+import { assert } from "chai";
 import { MPL_TOKEN_METADATA_PROGRAM_ID } from "@metaplex-foundation/mpl-token-metadata";
 
 describe("prediction_market", () => {
@@ -28,6 +29,8 @@ describe("prediction_market", () => {
     "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU"
   );
   let market: PublicKey;
+  let priceHistory: PublicKey;
+  let position: PublicKey;
   let vaultYes: PublicKey;
   let vaultNo: PublicKey;
   let vaultUSDC: PublicKey;
@@ -41,10 +44,27 @@ describe("prediction_market", () => {
   const marketName = "VIRAT_CENTURY_IND_NZ_CT_2025";
   const fee = 100;
   const endTime = new anchor.BN(Math.floor(Date.now() / 1000) + 86400);
+  const keeperTipBps = 50;
+  const limits = {
+    maxTradeSize: new anchor.BN(0),
+    maxUserExposure: new anchor.BN(0),
+    maxOpenInterest: new anchor.BN(0),
+  };
+  const initialPrice = new anchor.BN(500_000);
+  const circuitBreaker = {
+    maxPriceMove: new anchor.BN(0),
+    windowSlots: new anchor.BN(0),
+    maxPriceImpact: new anchor.BN(0),
+  };
 
   const uri_yes = "https://gateway.irys.xyz/52pWSqmBFhEr67znFS4KoK5UBpwgbJ1hHH6qQyUbkD6V";
   const uri_no = "https://gateway.irys.xyz/CPgxvKRwE6D4UVTvaWMbm3tJntvumZGpegXeALthdY5s";
 
+  const [eventAuthority] = PublicKey.findProgramAddressSync(
+    [Buffer.from("__event_authority")],
+    program.programId
+  );
+
   const getMetadataAddress = (mint: PublicKey): PublicKey => {
     return PublicKey.findProgramAddressSync(
       [
@@ -65,6 +85,16 @@ describe("prediction_market", () => {
     );
     market = marketPda;
 
+    [priceHistory] = PublicKey.findProgramAddressSync(
+      [Buffer.from("price_history"), market.toBuffer()],
+      program.programId
+    );
+
+    [position] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), market.toBuffer(), providerWallet.publicKey.toBuffer()],
+      program.programId
+    );
+
     const metadata_yes = {
       name: "VIRAT_YES",
       symbol: "VK_YES",
@@ -127,24 +157,31 @@ describe("prediction_market", () => {
           metadata_yes.name, metadata_yes.symbol, 
           metadata_no.name, metadata_no.symbol, 
           uri_yes, uri_no, 
-          fee, endTime
+          fee, endTime, keeperTipBps, limits, circuitBreaker, initialPrice,
+          { metaplex: {} }, 60, 0
         )
         .accountsStrict({
           signer: providerWallet.publicKey,
           mintYes,
           mintNo,
           mintUsdc: mintUSDC,
+          treasury: providerWallet.publicKey,
           vaultYes,
           vaultNo,
           vaultUsdc: vaultUSDC,
+          signerAtaUsdc: getAssociatedTokenAddressSync(mintUSDC, providerWallet.publicKey),
           market,
+          priceHistory,
           metadataYes: metadataYesAddress,
           metadataNo: metadataNoAddress,
           tokenProgram: TOKEN_PROGRAM_ID,
+          collateralTokenProgram: TOKEN_PROGRAM_ID,
           associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,
           rent: anchor.web3.SYSVAR_RENT_PUBKEY,
           tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+          eventAuthority,
+          program: program.programId,
         })
         .preInstructions([modifyComputeUnits])
         .rpc({ skipPreflight: true });
@@ -174,20 +211,27 @@ describe("prediction_market", () => {
         .addLiquidity(
           new anchor.BN(1_000_000_000),
           new anchor.BN(1_000_000_000),
-          new anchor.BN(Math.floor(Date.now() / 1000) + 3600)
+          new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+          []
         )
         .accountsStrict({
           market,
+          allowlistEntry: null,
+          position: null,
           mintNo,
           mintYes,
           vaultNo,
           vaultUsdc: vaultUSDC,
           vaultYes,
+          userAtaUsdc: userAtaUSDC,
           associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,
           mintUsdc: mintUSDC,
           user: providerWallet.publicKey,
           tokenProgram: TOKEN_PROGRAM_ID,
+          collateralTokenProgram: TOKEN_PROGRAM_ID,
+          eventAuthority,
+          program: program.programId,
         })
         .signers([providerWallet.payer])
         .rpc();
@@ -218,6 +262,22 @@ describe("prediction_market", () => {
     }
   });
 
+  it("Open position", async () => {
+    const tx = await program.methods
+      .openPosition()
+      .accountsStrict({
+        user: providerWallet.publicKey,
+        market,
+        position,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const account = await program.account.position.fetch(position);
+    assert.ok(account.user.equals(providerWallet.publicKey));
+    console.log("Position opened, transaction signature:", tx);
+  });
+
   it("Test LMSR pricing with multiple swaps", async () => {
     userAtaYes = getAssociatedTokenAddressSync(
       mintYes,
@@ -249,23 +309,32 @@ describe("prediction_market", () => {
             new anchor.BN(test.amount),
             test.isYes,
             new anchor.BN(1),
-            new anchor.BN(Math.floor(Date.now() / 1000) + 60)
+            new anchor.BN(Math.floor(Date.now() / 1000) + 60),
+            [],
+            null
           )
           .accountsStrict({
             userAtaNo,
             market,
+            priceHistory,
+            position,
+            allowlistEntry: null,
             mintNo,
             mintUsdc: mintUSDC,
             mintYes,
             user: providerWallet.publicKey,
             userAtaUsdc: userAtaUSDC,
+            unwrapAccount: null,
             userAtaYes,
             vaultNo,
             vaultUsdc: vaultUSDC,
             vaultYes,
             tokenProgram: TOKEN_PROGRAM_ID,
+            collateralTokenProgram: TOKEN_PROGRAM_ID,
             associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
             systemProgram: anchor.web3.SystemProgram.programId,
+            eventAuthority,
+            program: program.programId,
           })
           .signers([providerWallet.payer])
           .rpc();